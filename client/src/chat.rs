@@ -0,0 +1,352 @@
+// Chat box and quick-chat emotes. Enter opens the chat box (Enter sends, Esc closes) and keys
+// 1-4 send an emote that pops up above your truck for everyone. The last few messages stay on
+// screen for a while; while typing, driving keys are ignored.
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+use shared::{ClientToServer, Emote, PlayerId, WorldState, MAX_CHAT_CHARS};
+use std::collections::VecDeque;
+
+use crate::connection::ConnectionStatus;
+use crate::controls::RebindState;
+use crate::{ClientInfo, FollowCam, LocalPlayer, NetChannels, ServerPlayer};
+
+const LINE_SECS: f32 = 12.0; // How long a message stays on screen
+const MAX_LINES: usize = 8;
+const EMOTE_SECS: f32 = 3.0;
+const EMOTE_KEYS: [KeyCode; 4] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+];
+
+#[derive(Clone, Copy)]
+pub enum LineKind {
+    Chat,
+    Server, // Announcements from the server's admins
+    Notice, // Only for us, e.g. why a message was refused
+}
+
+struct ChatLine {
+    text: String,
+    kind: LineKind,
+    age: f32,
+}
+
+#[derive(Resource, Default)]
+pub struct ChatState {
+    pub typing: bool,
+    draft: String,
+    lines: VecDeque<ChatLine>,
+    new_emotes: Vec<(PlayerId, Emote)>, // Received but not shown yet
+}
+
+impl ChatState {
+    pub fn push(&mut self, text: String, kind: LineKind) {
+        self.lines.push_back(ChatLine {
+            text,
+            kind,
+            age: 0.0,
+        });
+        while self.lines.len() > MAX_LINES {
+            self.lines.pop_front();
+        }
+    }
+
+    pub fn show_emote(&mut self, from: PlayerId, emote: Emote) {
+        self.new_emotes.push((from, emote));
+    }
+}
+
+// How a player is named in chat until players have names; anyone without a truck in `world`
+// is a spectator
+pub fn speaker(from: PlayerId, me: Option<PlayerId>, world: Option<&WorldState>) -> String {
+    let short = &from.simple().to_string()[..4];
+    if me == Some(from) {
+        "You".to_string()
+    } else if world.is_some_and(|w| !w.players.contains_key(&from)) {
+        format!("Spectator {short}")
+    } else {
+        format!("Truck {short}")
+    }
+}
+
+#[derive(Component)]
+struct ChatLineText(usize); // Which of the visible lines, oldest first
+
+#[derive(Component)]
+struct ChatInputLine;
+
+#[derive(Component)]
+struct EmoteBubble {
+    player_id: PlayerId,
+    remaining: f32,
+}
+
+pub struct ChatPlugin;
+
+impl Plugin for ChatPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChatState>()
+            .add_systems(Startup, setup_chat_ui)
+            .add_systems(
+                Update,
+                (
+                    chat_keys,
+                    update_chat_box,
+                    spawn_emote_bubbles,
+                    update_emote_bubbles,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn send(chans: &NetChannels, msg: &ClientToServer) {
+    if let Some(tx) = &chans.to_server {
+        let _ = tx.unbounded_send(serde_json::to_string(msg).unwrap());
+    }
+}
+
+fn chat_keys(
+    mut key_events: MessageReader<KeyboardInput>,
+    status: Res<ConnectionStatus>,
+    rebind: Res<RebindState>,
+    client: Res<ClientInfo>,
+    chans: Res<NetChannels>,
+    mut chat: ResMut<ChatState>,
+) {
+    if *status != ConnectionStatus::Connected {
+        chat.typing = false;
+        key_events.clear();
+        return;
+    }
+    for event in key_events.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        if !chat.typing {
+            if event.logical_key == Key::Enter {
+                chat.typing = true;
+                chat.draft.clear();
+            } else if let Some(i) = EMOTE_KEYS.iter().position(|k| *k == event.key_code) {
+                // The controls panel uses the number keys to pick an action; spectators
+                // have no truck to show an emote over
+                if !rebind.open && !client.spectator && !event.repeat {
+                    send(&chans, &ClientToServer::Emote(Emote::ALL[i]));
+                }
+            }
+            continue;
+        }
+        match &event.logical_key {
+            Key::Enter => {
+                let text = chat.draft.trim().to_string();
+                if !text.is_empty() {
+                    send(&chans, &ClientToServer::Chat(text));
+                }
+                chat.typing = false;
+            }
+            Key::Escape => chat.typing = false,
+            Key::Backspace => {
+                chat.draft.pop();
+            }
+            _ => {
+                if let Some(text) = &event.text {
+                    let room = MAX_CHAT_CHARS.saturating_sub(chat.draft.chars().count());
+                    let typed: String = text
+                        .chars()
+                        .filter(|c| !c.is_control())
+                        .take(room)
+                        .collect();
+                    chat.draft.push_str(&typed);
+                }
+            }
+        }
+    }
+}
+
+fn setup_chat_ui(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Px(12.0),
+                bottom: Val::Px(12.0),
+                max_width: Val::Px(480.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::FlexStart,
+                ..default()
+            },
+            GlobalZIndex(4),
+        ))
+        .with_children(|parent| {
+            for i in 0..MAX_LINES {
+                parent.spawn((
+                    Text::new(""),
+                    TextFont {
+                        font_size: 16.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                    Node {
+                        display: Display::None,
+                        padding: UiRect::axes(Val::Px(6.0), Val::Px(1.0)),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.35)),
+                    ChatLineText(i),
+                ));
+            }
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font_size: 16.0,
+                    ..default()
+                },
+                Node {
+                    display: Display::None,
+                    margin: UiRect::top(Val::Px(4.0)),
+                    padding: UiRect::axes(Val::Px(6.0), Val::Px(3.0)),
+                    ..default()
+                },
+                BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+                ChatInputLine,
+            ));
+        });
+}
+
+fn update_chat_box(
+    time: Res<Time>,
+    mut chat: ResMut<ChatState>,
+    mut q_lines: Query<
+        (&ChatLineText, &mut Text, &mut TextColor, &mut Node),
+        Without<ChatInputLine>,
+    >,
+    mut q_input: Query<(&mut Text, &mut Node), With<ChatInputLine>>,
+) {
+    let dt = time.delta_secs();
+    for line in chat.lines.iter_mut() {
+        line.age += dt;
+    }
+    // Old messages fade out, but all recent ones show while typing
+    let typing = chat.typing;
+    let visible: Vec<&ChatLine> = chat
+        .lines
+        .iter()
+        .filter(|line| typing || line.age < LINE_SECS)
+        .collect();
+
+    for (ChatLineText(i), mut text, mut color, mut node) in q_lines.iter_mut() {
+        let Some(line) = visible.get(*i) else {
+            if node.display != Display::None {
+                node.display = Display::None;
+            }
+            continue;
+        };
+        if node.display != Display::Flex {
+            node.display = Display::Flex;
+        }
+        if text.0 != line.text {
+            text.0 = line.text.clone();
+        }
+        color.0 = match line.kind {
+            LineKind::Chat => Color::WHITE,
+            LineKind::Server => Color::srgb(1.0, 0.85, 0.3),
+            LineKind::Notice => Color::srgb(0.7, 0.7, 0.7),
+        };
+    }
+
+    if let Ok((mut text, mut node)) = q_input.single_mut() {
+        let display = if typing { Display::Flex } else { Display::None };
+        if node.display != display {
+            node.display = display;
+        }
+        if typing {
+            let cursor = format!("Say: {}_", chat.draft);
+            if text.0 != cursor {
+                text.0 = cursor;
+            }
+        }
+    }
+}
+
+fn spawn_emote_bubbles(mut commands: Commands, mut chat: ResMut<ChatState>) {
+    for (player_id, emote) in chat.new_emotes.drain(..) {
+        commands.spawn((
+            Text::new(emote.text()),
+            TextFont {
+                font_size: 22.0,
+                ..default()
+            },
+            TextColor(Color::srgb(1.0, 0.95, 0.6)),
+            Node {
+                position_type: PositionType::Absolute,
+                padding: UiRect::axes(Val::Px(8.0), Val::Px(3.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.55)),
+            Visibility::Hidden,
+            GlobalZIndex(3),
+            EmoteBubble {
+                player_id,
+                remaining: EMOTE_SECS,
+            },
+        ));
+    }
+}
+
+// Keep each bubble over its truck's roof, and remove it when its time is up
+fn update_emote_bubbles(
+    time: Res<Time>,
+    mut commands: Commands,
+    client: Res<ClientInfo>,
+    q_cam: Query<(&Camera, &GlobalTransform), With<FollowCam>>,
+    q_server: Query<(&ServerPlayer, &GlobalTransform)>,
+    q_local: Query<(&LocalPlayer, &GlobalTransform)>,
+    mut q_bubbles: Query<(
+        Entity,
+        &mut EmoteBubble,
+        &mut Node,
+        &mut Visibility,
+        &ComputedNode,
+    )>,
+) {
+    let Ok((camera, cam_transform)) = q_cam.single() else {
+        return;
+    };
+    for (entity, mut bubble, mut node, mut visibility, computed) in q_bubbles.iter_mut() {
+        bubble.remaining -= time.delta_secs();
+        if bubble.remaining <= 0.0 {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        let truck = if client.id == Some(bubble.player_id) {
+            q_local
+                .iter()
+                .find(|(p, _)| p.id == bubble.player_id)
+                .map(|(_, t)| t.translation())
+        } else {
+            q_server
+                .iter()
+                .find(|(p, _)| p.id == bubble.player_id)
+                .map(|(_, t)| t.translation())
+        };
+        let screen = truck.and_then(|pos| {
+            camera
+                .world_to_viewport(cam_transform, pos + Vec3::Y * 2.5)
+                .ok()
+        });
+        match screen {
+            Some(screen) => {
+                // Centred over the truck; ComputedNode sizes are in physical pixels
+                let size = computed.size() * computed.inverse_scale_factor();
+                node.left = Val::Px(screen.x - size.x * 0.5);
+                node.top = Val::Px(screen.y - size.y);
+                *visibility = Visibility::Inherited;
+            }
+            // Off screen, or the truck isn't spawned (yet)
+            None => *visibility = Visibility::Hidden,
+        }
+    }
+}
@@ -0,0 +1,658 @@
+// Connection to the game server: a background task that connects (with our profile token),
+// reconnects with backoff (resuming our truck with the session token from Welcome) and reports
+// what it is doing, plus a panel showing that status with the server URL and a retry button.
+//
+// While the panel is up, click the URL (or just start typing) to edit it; Enter or the Retry
+// button connects to it. Changing the URL starts a new session on the new server.
+use crate::profile::LocalProfile;
+use crate::NetChannels;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+#[cfg(target_arch = "wasm32")]
+use futures::StreamExt;
+#[cfg(not(target_arch = "wasm32"))]
+use futures::{SinkExt, StreamExt};
+use shared::{
+    ServerToClient, CLOSE_KICKED, CLOSE_SERVER_FULL, CLOSE_VERSION_MISMATCH, PROTOCOL_VERSION,
+};
+use std::sync::{Arc, Mutex};
+
+// Delay before reconnecting after a dropped or failed connection, doubling up to the max
+const RECONNECT_MIN_MS: u32 = 500;
+const RECONNECT_MAX_MS: u32 = 10_000;
+// Consecutive failed attempts before giving up and waiting for the player to retry. A
+// connection only counts as a success once the server has welcomed us, so one that is closed
+// straight after opening still counts towards this.
+const MAX_ATTEMPTS: u32 = 8;
+// Standard close code the server uses when it drops us for protocol errors
+const CLOSE_POLICY: u16 = 1008;
+
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub enum ConnectionStatus {
+    Connecting { attempt: u32 }, // Never connected to this server yet
+    Connected,
+    Reconnecting { attempt: u32 }, // Lost the connection; trying to resume our truck
+    Failed(String),                // Gave up after MAX_ATTEMPTS
+    ServerFull,
+    VersionMismatch(String), // Server's explanation, e.g. which versions each side speaks
+    Kicked(String),          // Removed or banned by an admin, with their reason
+    ShuttingDown { reason: String, seconds: u32 }, // Still connected, but not for long
+}
+
+impl Default for ConnectionStatus {
+    fn default() -> Self {
+        ConnectionStatus::Connecting { attempt: 1 }
+    }
+}
+
+impl ConnectionStatus {
+    pub fn message(&self) -> String {
+        match self {
+            ConnectionStatus::Connecting { attempt: 1 } => "Connecting to server...".to_string(),
+            ConnectionStatus::Connecting { attempt } => {
+                format!("Connecting to server... (attempt {attempt}/{MAX_ATTEMPTS})")
+            }
+            ConnectionStatus::Connected => "Connected".to_string(),
+            ConnectionStatus::Reconnecting { attempt } => {
+                format!("Connection lost, reconnecting... (attempt {attempt}/{MAX_ATTEMPTS})")
+            }
+            ConnectionStatus::Failed(error) => format!("Could not connect: {error}"),
+            ConnectionStatus::ServerFull => "Server is full, try again later".to_string(),
+            ConnectionStatus::VersionMismatch(reason) => {
+                format!("Game version doesn't match the server ({reason}); update the game")
+            }
+            ConnectionStatus::Kicked(reason) => format!("Removed from the server: {reason}"),
+            ConnectionStatus::ShuttingDown { reason, seconds } => {
+                format!("Disconnecting in {seconds}s: {reason}")
+            }
+        }
+    }
+
+    // The connection task has stopped and waits for the player to retry
+    fn stopped(&self) -> bool {
+        matches!(
+            self,
+            ConnectionStatus::Failed(_)
+                | ConnectionStatus::ServerFull
+                | ConnectionStatus::VersionMismatch(_)
+                | ConnectionStatus::Kicked(_)
+        )
+    }
+}
+
+// Server URL as shown (and edited) in the panel
+#[derive(Resource)]
+struct ServerUrl {
+    url: String,
+    connected_to: String, // URL of the running connection task
+    editing: bool,
+}
+
+#[derive(Component)]
+struct ConnectionPanel;
+
+#[derive(Component)]
+struct ConnectionPanelText;
+
+#[derive(Component)]
+struct ServerUrlField;
+
+#[derive(Component)]
+struct ServerUrlText;
+
+#[derive(Component)]
+struct RetryButton;
+
+pub struct ConnectionPlugin;
+
+impl Plugin for ConnectionPlugin {
+    fn build(&self, app: &mut App) {
+        let url = default_server_url();
+        app.insert_resource(ServerUrl {
+            connected_to: url.clone(),
+            url,
+            editing: false,
+        })
+        .insert_resource(LocalProfile::load())
+        .init_resource::<ConnectionStatus>()
+        .add_systems(Startup, (net_connect, setup_connection_ui))
+        .add_systems(
+            Update,
+            (
+                poll_connection_status,
+                edit_server_url,
+                update_connection_panel,
+            )
+                .chain(),
+        );
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn default_server_url() -> String {
+    std::env::var("SERVER_WS_URL").unwrap_or_else(|_| "ws://127.0.0.1:4001/ws".to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn default_server_url() -> String {
+    let window = web_sys::window().expect("no global `window` exists");
+    let location = window.location();
+
+    // Check if custom server URL is provided via query parameter
+    let custom_url = if let Ok(url_params) =
+        web_sys::UrlSearchParams::new_with_str(location.search().unwrap_or_default().as_str())
+    {
+        url_params.get("server")
+    } else {
+        None
+    };
+
+    if let Some(server_url) = custom_url {
+        server_url
+    } else {
+        // Determine WebSocket URL based on environment
+        let hostname = location.hostname().unwrap_or_default();
+        let port = location.port().unwrap_or_default();
+        let protocol = if location.protocol().unwrap_or_default() == "https:" {
+            "wss:"
+        } else {
+            "ws:"
+        };
+
+        // For localhost development on non-standard port, connect directly to server
+        if (hostname == "127.0.0.1" || hostname == "localhost") && port != "80" && !port.is_empty()
+        {
+            "ws://127.0.0.1:4001/ws".to_string()
+        } else {
+            // Production or localhost on port 80: use nginx proxy (same host/port as page)
+            format!("{}//{}/ws", protocol, location.host().unwrap_or_default())
+        }
+    }
+}
+
+fn net_connect(mut chans: ResMut<NetChannels>, server: Res<ServerUrl>, profile: Res<LocalProfile>) {
+    if chans.to_server.is_none() {
+        connect(&mut chans, &server.url, &profile.token);
+    }
+}
+
+// Start a connection task for `url`, replacing any running one (it notices its channels are
+// gone and stops)
+fn connect(chans: &mut NetChannels, url: &str, profile: &str) {
+    let (tx_out, rx_out) = unbounded::<String>();
+    let (tx_in, rx_in) = unbounded::<String>();
+    chans.to_server = Some(tx_out);
+    chans.from_server = Some(rx_in);
+    chans.status = Arc::new(Mutex::new(ConnectionStatus::default()));
+    let backoff = Backoff {
+        status: chans.status.clone(),
+        failures: 0,
+        delay_ms: RECONNECT_MIN_MS,
+        connected_once: false,
+    };
+    spawn_connection(
+        url.to_string(),
+        chans.session.clone(),
+        profile.to_string(),
+        backoff,
+        rx_out,
+        tx_in,
+    );
+}
+
+// Server URL with our protocol version, profile and the session token to resume, if we have
+// one, or the request to join as a spectator
+fn connect_url(url: &str, session: &Mutex<Option<String>>, profile: &str) -> String {
+    let sep = if url.contains('?') { '&' } else { '?' };
+    if crate::spectate::spectate_requested() {
+        return format!("{url}{sep}v={PROTOCOL_VERSION}&spectate=true");
+    }
+    match session.lock().unwrap().as_deref() {
+        Some(token) => format!("{url}{sep}v={PROTOCOL_VERSION}&profile={profile}&resume={token}"),
+        None => format!("{url}{sep}v={PROTOCOL_VERSION}&profile={profile}"),
+    }
+}
+
+// Reconnect delays and status reporting shared by the native and web connection tasks
+struct Backoff {
+    status: Arc<Mutex<ConnectionStatus>>,
+    failures: u32, // Since the last connection the server welcomed us on
+    delay_ms: u32,
+    connected_once: bool,
+}
+
+impl Backoff {
+    fn set(&self, status: ConnectionStatus) {
+        *self.status.lock().unwrap() = status;
+    }
+
+    fn opened(&mut self) {
+        self.connected_once = true;
+        self.set(ConnectionStatus::Connected);
+    }
+
+    // The connection failed or closed (with the server's close code and reason, if it sent
+    // one), `welcomed` if the server had accepted us on it. Returns how long to wait before the
+    // next attempt, or None to stop retrying.
+    fn closed(&mut self, close: Option<(u16, String)>, error: &str, welcomed: bool) -> Option<u32> {
+        if welcomed {
+            self.failures = 0;
+            self.delay_ms = RECONNECT_MIN_MS;
+        }
+        match close {
+            Some((CLOSE_SERVER_FULL, _)) => {
+                self.set(ConnectionStatus::ServerFull);
+                return None;
+            }
+            Some((CLOSE_VERSION_MISMATCH, reason)) => {
+                self.set(ConnectionStatus::VersionMismatch(reason));
+                return None;
+            }
+            Some((CLOSE_KICKED, reason)) => {
+                self.set(ConnectionStatus::Kicked(reason));
+                return None;
+            }
+            // Reconnecting would only repeat whatever the server objected to
+            Some((CLOSE_POLICY, reason)) => {
+                self.set(ConnectionStatus::Failed(format!(
+                    "disconnected by the server: {reason}"
+                )));
+                return None;
+            }
+            _ => {}
+        }
+        self.failures += 1;
+        if self.failures >= MAX_ATTEMPTS {
+            self.set(ConnectionStatus::Failed(error.to_string()));
+            return None;
+        }
+        let attempt = self.failures + 1;
+        self.set(if self.connected_once {
+            ConnectionStatus::Reconnecting { attempt }
+        } else {
+            ConnectionStatus::Connecting { attempt }
+        });
+        let delay = self.delay_ms;
+        self.delay_ms = (self.delay_ms * 2).min(RECONNECT_MAX_MS);
+        Some(delay)
+    }
+}
+
+// The server accepted us: it sends Welcome once we are in the game
+fn is_welcome(text: &str) -> bool {
+    matches!(
+        serde_json::from_str::<ServerToClient>(text),
+        Ok(ServerToClient::Welcome { .. })
+    )
+}
+
+// Keep reconnecting with backoff until the server turns us away, we run out of attempts or the
+// connection is replaced. Each connection after the first carries the session token so the
+// server hands back the same truck.
+#[cfg(not(target_arch = "wasm32"))]
+fn spawn_connection(
+    url: String,
+    session: Arc<Mutex<Option<String>>>,
+    profile: String,
+    mut backoff: Backoff,
+    mut rx_out: UnboundedReceiver<String>,
+    tx_in: UnboundedSender<String>,
+) {
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Runtime::new().expect("tokio runtime");
+        rt.block_on(async move {
+            use tokio_tungstenite::connect_async;
+            use tungstenite::Message;
+            while !tx_in.is_closed() {
+                let (close, error, welcomed) = match connect_async(connect_url(&url, &session, &profile)).await {
+                    Ok((ws, _)) => {
+                        backoff.opened();
+                        // Inputs queued while we were disconnected are stale
+                        while let Ok(Some(_)) = rx_out.try_next() {}
+                        let (mut write, mut read) = ws.split();
+                        let mut close = None;
+                        let mut welcomed = false;
+                        loop {
+                            tokio::select! {
+                                msg = read.next() => match msg {
+                                    Some(Ok(Message::Close(frame))) => {
+                                        close = frame.map(|f| (u16::from(f.code), f.reason.into_owned()));
+                                        break;
+                                    }
+                                    Some(Ok(msg)) => {
+                                        if !msg.is_text() {
+                                            continue;
+                                        }
+                                        let text = msg.into_text().unwrap();
+                                        welcomed = welcomed || is_welcome(&text);
+                                        if tx_in.unbounded_send(text).is_err() {
+                                            return;
+                                        }
+                                    }
+                                    _ => break,
+                                },
+                                out = rx_out.next() => match out {
+                                    Some(out) => {
+                                        if write.send(Message::Text(out)).await.is_err() {
+                                            break;
+                                        }
+                                    }
+                                    // The connection was replaced or the app has shut down
+                                    None => return,
+                                },
+                            }
+                        }
+                        log::warn!("websocket connection lost");
+                        (close, "connection lost".to_string(), welcomed)
+                    }
+                    Err(e) => {
+                        log::error!("websocket connect error: {e}");
+                        (None, e.to_string(), false)
+                    }
+                };
+                let Some(delay_ms) = backoff.closed(close, &error, welcomed) else {
+                    return;
+                };
+                log::info!("reconnecting in {delay_ms} ms");
+                tokio::time::sleep(std::time::Duration::from_millis(delay_ms as u64)).await;
+            }
+        });
+    });
+}
+
+#[cfg(target_arch = "wasm32")]
+fn spawn_connection(
+    url: String,
+    session: Arc<Mutex<Option<String>>>,
+    profile: String,
+    mut backoff: Backoff,
+    mut rx_out: UnboundedReceiver<String>,
+    tx_in: UnboundedSender<String>,
+) {
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+    use wasm_bindgen::prelude::*;
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen_futures::spawn_local;
+    use web_sys::{CloseEvent, ErrorEvent, MessageEvent, WebSocket};
+
+    // The socket of the current connection, None while reconnecting
+    let current: Rc<RefCell<Option<WebSocket>>> = Rc::default();
+
+    // write: send to the open socket, dropping messages while disconnected
+    {
+        let current = current.clone();
+        spawn_local(async move {
+            while let Some(out) = rx_out.next().await {
+                if let Some(ws) = current.borrow().as_ref() {
+                    if ws.ready_state() == WebSocket::OPEN {
+                        if let Err(e) = ws.send_with_str(&out) {
+                            log::error!("Failed to send WebSocket message: {:?}", e);
+                        }
+                    }
+                }
+            }
+            // The connection was replaced or the app has shut down
+            if let Some(ws) = current.borrow_mut().take() {
+                let _ = ws.close();
+            }
+        });
+    }
+
+    spawn_local(async move {
+        while !tx_in.is_closed() {
+            log::info!("Attempting to connect to WebSocket: {}", url);
+            let ws_url = connect_url(&url, &session, &profile);
+            let (close, error, welcomed) = match WebSocket::new(&ws_url) {
+                Ok(ws) => {
+                    ws.set_binary_type(web_sys::BinaryType::Arraybuffer);
+                    let opened = Rc::new(Cell::new(false));
+                    let welcomed = Rc::new(Cell::new(false));
+                    let (closed_tx, closed_rx) =
+                        futures::channel::oneshot::channel::<(u16, String)>();
+
+                    let onopen = {
+                        let url_for_log = url.clone();
+                        let opened = opened.clone();
+                        let status = backoff.status.clone();
+                        Closure::<dyn FnMut(web_sys::Event)>::new(move |_| {
+                            log::info!("WebSocket connected to {}", url_for_log);
+                            opened.set(true);
+                            *status.lock().unwrap() = ConnectionStatus::Connected;
+                        })
+                    };
+                    ws.set_onopen(Some(onopen.as_ref().unchecked_ref()));
+
+                    // Fires after both failed connects and dropped connections
+                    let onclose = {
+                        let mut closed_tx = Some(closed_tx);
+                        Closure::<dyn FnMut(CloseEvent)>::new(move |e: CloseEvent| {
+                            log::warn!("WebSocket connection closed ({})", e.code());
+                            if let Some(tx) = closed_tx.take() {
+                                let _ = tx.send((e.code(), e.reason()));
+                            }
+                        })
+                    };
+                    ws.set_onclose(Some(onclose.as_ref().unchecked_ref()));
+
+                    let onmessage = {
+                        let tx_in = tx_in.clone();
+                        let ws = ws.clone();
+                        let welcomed = welcomed.clone();
+                        Closure::<dyn FnMut(_)>::new(move |e: MessageEvent| {
+                            if let Ok(txt) = e.data().dyn_into::<js_sys::JsString>() {
+                                let text = String::from(txt);
+                                if !welcomed.get() && is_welcome(&text) {
+                                    welcomed.set(true);
+                                }
+                                if tx_in.unbounded_send(text).is_err() {
+                                    let _ = ws.close();
+                                }
+                            }
+                        })
+                    };
+                    ws.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+
+                    let onerror = Closure::<dyn FnMut(_)>::new(move |_e: ErrorEvent| {
+                        // ErrorEvent.message() may not be available in all browsers
+                        log::error!("WebSocket error occurred");
+                    });
+                    ws.set_onerror(Some(onerror.as_ref().unchecked_ref()));
+
+                    *current.borrow_mut() = Some(ws.clone());
+                    let close = closed_rx.await.ok();
+                    *current.borrow_mut() = None;
+                    ws.set_onopen(None);
+                    ws.set_onclose(None);
+                    ws.set_onmessage(None);
+                    ws.set_onerror(None);
+                    let error = if opened.get() {
+                        backoff.opened();
+                        "connection lost"
+                    } else {
+                        // Browsers don't say why a WebSocket failed to open
+                        "server unreachable"
+                    };
+                    (close, error.to_string(), welcomed.get())
+                }
+                Err(e) => {
+                    log::error!("Failed to create WebSocket: {:?}", e);
+                    (None, "invalid server URL".to_string(), false)
+                }
+            };
+            let Some(delay_ms) = backoff.closed(close, &error, welcomed) else {
+                return;
+            };
+            log::info!("Reconnecting in {} ms", delay_ms);
+            sleep_ms(delay_ms).await;
+        }
+    });
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn sleep_ms(ms: u32) {
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        let _ = web_sys::window()
+            .expect("no global `window` exists")
+            .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, ms as i32);
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
+
+// Copy the connection task's status into the ConnectionStatus resource
+fn poll_connection_status(chans: Res<NetChannels>, mut status: ResMut<ConnectionStatus>) {
+    let current = chans.status.lock().unwrap().clone();
+    status.set_if_neq(current);
+}
+
+fn edit_server_url(
+    mut key_events: MessageReader<KeyboardInput>,
+    q_field: Query<&Interaction, (Changed<Interaction>, With<ServerUrlField>)>,
+    q_retry: Query<&Interaction, (Changed<Interaction>, With<RetryButton>)>,
+    status: Res<ConnectionStatus>,
+    mut server: ResMut<ServerUrl>,
+    mut chans: ResMut<NetChannels>,
+    profile: Res<LocalProfile>,
+) {
+    if *status == ConnectionStatus::Connected {
+        server.editing = false;
+        key_events.clear();
+        return;
+    }
+    if q_field.iter().any(|i| *i == Interaction::Pressed) {
+        server.editing = true;
+    }
+    let mut retry = q_retry.iter().any(|i| *i == Interaction::Pressed);
+    for event in key_events.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        match &event.logical_key {
+            Key::Enter => retry = true,
+            Key::Escape => server.editing = false,
+            Key::Backspace if server.editing || status.stopped() => {
+                server.editing = true;
+                server.url.pop();
+            }
+            _ => {
+                if let Some(text) = &event.text {
+                    if server.editing || status.stopped() {
+                        server.editing = true;
+                        server.url.extend(text.chars().filter(|c| !c.is_control()));
+                    }
+                }
+            }
+        }
+    }
+    if retry && !server.url.trim().is_empty() {
+        server.editing = false;
+        server.url = server.url.trim().to_string();
+        if server.url != server.connected_to {
+            // A different server won't know our session
+            *chans.session.lock().unwrap() = None;
+            server.connected_to = server.url.clone();
+        }
+        connect(&mut chans, &server.url, &profile.token);
+    }
+}
+
+fn setup_connection_ui(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Percent(60.0),
+                width: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                display: Display::None,
+                ..default()
+            },
+            GlobalZIndex(10),
+            ConnectionPanel,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font_size: 18.0,
+                    ..default()
+                },
+                Node {
+                    margin: UiRect::all(Val::Px(8.0)),
+                    ..default()
+                },
+                ConnectionPanelText,
+            ));
+            parent
+                .spawn((
+                    Button,
+                    Node {
+                        min_width: Val::Px(360.0),
+                        padding: UiRect::axes(Val::Px(10.0), Val::Px(6.0)),
+                        margin: UiRect::all(Val::Px(6.0)),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgba(0.1, 0.1, 0.15, 0.9)),
+                    ServerUrlField,
+                ))
+                .with_children(|field| {
+                    field.spawn((
+                        Text::new(""),
+                        TextFont {
+                            font_size: 16.0,
+                            ..default()
+                        },
+                        ServerUrlText,
+                    ));
+                });
+            parent
+                .spawn((
+                    Button,
+                    Node {
+                        padding: UiRect::axes(Val::Px(24.0), Val::Px(8.0)),
+                        margin: UiRect::all(Val::Px(6.0)),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.15, 0.45, 0.6)),
+                    RetryButton,
+                ))
+                .with_children(|button| {
+                    button.spawn(Text::new("Retry"));
+                });
+        });
+}
+
+fn update_connection_panel(
+    status: Res<ConnectionStatus>,
+    server: Res<ServerUrl>,
+    mut q_panel: Query<(&mut Node, &mut BackgroundColor), With<ConnectionPanel>>,
+    mut q_text: Query<&mut Text, (With<ConnectionPanelText>, Without<ServerUrlText>)>,
+    mut q_url: Query<&mut Text, (With<ServerUrlText>, Without<ConnectionPanelText>)>,
+) {
+    if !status.is_changed() && !server.is_changed() {
+        return;
+    }
+    if let Ok((mut node, mut background)) = q_panel.single_mut() {
+        node.display = if *status == ConnectionStatus::Connected {
+            Display::None
+        } else {
+            Display::Flex
+        };
+        // Darken the game behind the panel once we've given up
+        *background = BackgroundColor(if status.stopped() {
+            Color::srgba(0.0, 0.0, 0.0, 0.5)
+        } else {
+            Color::NONE
+        });
+    }
+    if let Ok(mut text) = q_text.single_mut() {
+        *text = Text::new(status.message());
+    }
+    if let Ok(mut text) = q_url.single_mut() {
+        let cursor = if server.editing { "_" } else { "" };
+        *text = Text::new(format!("Server: {}{cursor}", server.url));
+    }
+}
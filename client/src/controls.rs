@@ -0,0 +1,495 @@
+// Player controls: rebindable keyboard keys, gamepads and an on-screen touch joystick with a
+// boost button, combined each frame into one analog steer value and a boost flag.
+//
+// F1 opens the controls panel: press 1-3 to pick an action, then the key to bind it to
+// (Esc cancels, Backspace restores the defaults). Bindings are saved to controls.json next to
+// the game natively and to localStorage on web.
+use bevy::input::InputSystems;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use serde::{Deserialize, Serialize};
+
+use crate::chat::ChatState;
+
+#[cfg(target_arch = "wasm32")]
+const STORAGE_KEY: &str = "hover_truck_controls";
+#[cfg(not(target_arch = "wasm32"))]
+const SETTINGS_PATH: &str = "controls.json";
+
+const GAMEPAD_DEADZONE: f32 = 0.15;
+const TOUCH_STICK_RADIUS: f32 = 70.0; // Drag distance in pixels for full steering lock
+const TOUCH_BOOST_SIZE: f32 = 110.0;
+
+// This frame's input for the local truck: steer is -1 (full right) to 1 (full left) like
+// `ClientToServer::Input`
+#[derive(Resource, Default, Clone, Copy)]
+pub struct PlayerControls {
+    pub steer: f32,
+    pub boost: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Action {
+    SteerLeft,
+    SteerRight,
+    Boost,
+}
+
+impl Action {
+    const ALL: [Action; 3] = [Action::SteerLeft, Action::SteerRight, Action::Boost];
+
+    fn label(self) -> &'static str {
+        match self {
+            Action::SteerLeft => "Steer left",
+            Action::SteerRight => "Steer right",
+            Action::Boost => "Boost",
+        }
+    }
+}
+
+#[derive(Resource, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub steer_left: Vec<KeyCode>,
+    pub steer_right: Vec<KeyCode>,
+    pub boost: Vec<KeyCode>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        // Arrow keys stay free for the debug test player
+        Self {
+            steer_left: vec![KeyCode::KeyA],
+            steer_right: vec![KeyCode::KeyD],
+            boost: vec![KeyCode::KeyW],
+        }
+    }
+}
+
+impl KeyBindings {
+    fn keys(&self, action: Action) -> &Vec<KeyCode> {
+        match action {
+            Action::SteerLeft => &self.steer_left,
+            Action::SteerRight => &self.steer_right,
+            Action::Boost => &self.boost,
+        }
+    }
+
+    fn keys_mut(&mut self, action: Action) -> &mut Vec<KeyCode> {
+        match action {
+            Action::SteerLeft => &mut self.steer_left,
+            Action::SteerRight => &mut self.steer_right,
+            Action::Boost => &mut self.boost,
+        }
+    }
+
+    fn pressed(&self, keys: &ButtonInput<KeyCode>, action: Action) -> bool {
+        keys.any_pressed(self.keys(action).iter().copied())
+    }
+
+    // Bind `key` to `action` alone, taking it away from any other action
+    fn rebind(&mut self, action: Action, key: KeyCode) {
+        for other in Action::ALL {
+            self.keys_mut(other).retain(|k| *k != key);
+        }
+        *self.keys_mut(action) = vec![key];
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct RebindState {
+    pub open: bool, // The controls panel is showing
+    selected: Option<Action>,
+    status: String,
+}
+
+// Which touches are driving the stick and the boost button
+#[derive(Resource, Default)]
+struct TouchState {
+    seen: bool, // Touch controls are only shown once the screen has been touched
+    stick: Option<(u64, Vec2)>, // Touch id and where it started
+    boost: Option<u64>,
+}
+
+#[derive(Component)]
+struct ControlsPanel;
+
+#[derive(Component)]
+struct ControlsPanelText;
+
+#[derive(Component)]
+struct TouchStickBase;
+
+#[derive(Component)]
+struct TouchStickKnob;
+
+#[derive(Component)]
+struct TouchBoostButton;
+
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        let bindings = match load_bindings() {
+            Ok(Some(bindings)) => bindings,
+            Ok(None) => KeyBindings::default(),
+            Err(e) => {
+                warn!("Ignoring saved key bindings: {e}");
+                KeyBindings::default()
+            }
+        };
+        app.insert_resource(bindings)
+            .init_resource::<PlayerControls>()
+            .init_resource::<RebindState>()
+            .init_resource::<TouchState>()
+            .add_systems(Startup, setup_controls_ui)
+            .add_systems(PreUpdate, read_player_controls.after(InputSystems))
+            .add_systems(
+                Update,
+                (rebind_keys, update_controls_panel, update_touch_ui),
+            );
+    }
+}
+
+fn read_player_controls(
+    keys: Res<ButtonInput<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    rebind: Res<RebindState>,
+    chat: Res<ChatState>,
+    gamepads: Query<&Gamepad>,
+    touches: Res<Touches>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    mut touch: ResMut<TouchState>,
+    mut controls: ResMut<PlayerControls>,
+) {
+    // Keyboard (ignored while picking a new key or typing in chat so the key press doesn't
+    // also steer)
+    let mut key_steer = 0.0;
+    let mut key_boost = false;
+    if rebind.selected.is_none() && !chat.typing {
+        if bindings.pressed(&keys, Action::SteerLeft) {
+            key_steer += 1.0;
+        }
+        if bindings.pressed(&keys, Action::SteerRight) {
+            key_steer -= 1.0;
+        }
+        key_boost = bindings.pressed(&keys, Action::Boost);
+    }
+
+    // Gamepads: left stick or d-pad to steer, south button or right trigger to boost
+    let mut pad_steer = 0.0;
+    let mut pad_boost = false;
+    for gamepad in gamepads.iter() {
+        let stick = gamepad.left_stick().x;
+        if stick.abs() > GAMEPAD_DEADZONE {
+            // Rescale past the deadzone so small deflections still give gentle turns
+            pad_steer =
+                -stick.signum() * (stick.abs() - GAMEPAD_DEADZONE) / (1.0 - GAMEPAD_DEADZONE);
+        } else if gamepad.pressed(GamepadButton::DPadLeft) {
+            pad_steer = 1.0;
+        } else if gamepad.pressed(GamepadButton::DPadRight) {
+            pad_steer = -1.0;
+        }
+        pad_boost |= gamepad.pressed(GamepadButton::South)
+            || gamepad.pressed(GamepadButton::RightTrigger)
+            || gamepad.pressed(GamepadButton::RightTrigger2);
+    }
+
+    // Touch: a drag starting on the left half of the screen is the joystick, any touch on
+    // the right half holds boost
+    let half_width = q_window.single().map_or(f32::MAX, |w| w.width() * 0.5);
+    for t in touches.iter_just_pressed() {
+        touch.seen = true;
+        if t.position().x < half_width {
+            if touch.stick.is_none() {
+                touch.stick = Some((t.id(), t.position()));
+            }
+        } else if touch.boost.is_none() {
+            touch.boost = Some(t.id());
+        }
+    }
+    if touch
+        .stick
+        .is_some_and(|(id, _)| touches.get_pressed(id).is_none())
+    {
+        touch.stick = None;
+    }
+    if touch
+        .boost
+        .is_some_and(|id| touches.get_pressed(id).is_none())
+    {
+        touch.boost = None;
+    }
+    let touch_steer = touch
+        .stick
+        .and_then(|(id, start)| {
+            let t = touches.get_pressed(id)?;
+            Some((-(t.position().x - start.x) / TOUCH_STICK_RADIUS).clamp(-1.0, 1.0))
+        })
+        .unwrap_or(0.0);
+
+    // First source with any steering wins, so a resting stick doesn't cancel the keyboard
+    controls.steer = [key_steer, pad_steer, touch_steer]
+        .into_iter()
+        .find(|s: &f32| *s != 0.0)
+        .unwrap_or(0.0);
+    controls.boost = key_boost || pad_boost || touch.boost.is_some();
+}
+
+fn rebind_keys(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut bindings: ResMut<KeyBindings>,
+    mut rebind: ResMut<RebindState>,
+    chat: Res<ChatState>,
+) {
+    if chat.typing {
+        return;
+    }
+    if keys.just_pressed(KeyCode::F1) {
+        rebind.open = !rebind.open;
+        rebind.selected = None;
+        rebind.status.clear();
+        return;
+    }
+    if !rebind.open {
+        return;
+    }
+
+    if let Some(action) = rebind.selected {
+        let Some(&key) = keys.get_just_pressed().next() else {
+            return;
+        };
+        rebind.selected = None;
+        if key == KeyCode::Escape {
+            rebind.status = "Cancelled".to_string();
+            return;
+        }
+        bindings.rebind(action, key);
+        rebind.status = save_status(&bindings, format!("{} bound to {key:?}", action.label()));
+        return;
+    }
+
+    let digits = [KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3];
+    for (action, digit) in Action::ALL.into_iter().zip(digits) {
+        if keys.just_pressed(digit) {
+            rebind.selected = Some(action);
+            rebind.status = format!("Press a key for {} (Esc cancels)", action.label());
+        }
+    }
+    if keys.just_pressed(KeyCode::Backspace) {
+        *bindings = KeyBindings::default();
+        rebind.status = save_status(&bindings, "Defaults restored".to_string());
+    }
+}
+
+fn save_status(bindings: &KeyBindings, done: String) -> String {
+    match save_bindings(bindings) {
+        Ok(()) => done,
+        Err(e) => format!("{done} (not saved: {e})"),
+    }
+}
+
+fn setup_controls_ui(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(60.0),
+                left: Val::Px(20.0),
+                padding: UiRect::all(Val::Px(12.0)),
+                display: Display::None,
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.1, 0.1, 0.15, 0.9)),
+            ZIndex(20),
+            ControlsPanel,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font_size: 16.0,
+                    ..default()
+                },
+                ControlsPanelText,
+            ));
+        });
+
+    // Touch joystick (shown where the drag starts) and boost button, hidden until touched
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Px(TOUCH_STICK_RADIUS * 2.0),
+                height: Val::Px(TOUCH_STICK_RADIUS * 2.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                display: Display::None,
+                ..default()
+            },
+            BorderRadius::MAX,
+            BackgroundColor(Color::srgba(1.0, 1.0, 1.0, 0.12)),
+            TouchStickBase,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Node {
+                    width: Val::Px(TOUCH_STICK_RADIUS * 0.7),
+                    height: Val::Px(TOUCH_STICK_RADIUS * 0.7),
+                    ..default()
+                },
+                BorderRadius::MAX,
+                BackgroundColor(Color::srgba(1.0, 1.0, 1.0, 0.35)),
+                TouchStickKnob,
+            ));
+        });
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                right: Val::Px(30.0),
+                bottom: Val::Px(30.0),
+                width: Val::Px(TOUCH_BOOST_SIZE),
+                height: Val::Px(TOUCH_BOOST_SIZE),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                display: Display::None,
+                ..default()
+            },
+            BorderRadius::MAX,
+            BackgroundColor(Color::srgba(0.95, 0.6, 0.1, 0.3)),
+            TouchBoostButton,
+        ))
+        .with_children(|parent| {
+            parent.spawn(Text::new("BOOST"));
+        });
+}
+
+fn update_controls_panel(
+    bindings: Res<KeyBindings>,
+    rebind: Res<RebindState>,
+    mut q_panel: Query<&mut Node, With<ControlsPanel>>,
+    mut q_text: Query<&mut Text, With<ControlsPanelText>>,
+) {
+    if !bindings.is_changed() && !rebind.is_changed() {
+        return;
+    }
+    if let Ok(mut node) = q_panel.single_mut() {
+        node.display = if rebind.open {
+            Display::Flex
+        } else {
+            Display::None
+        };
+    }
+    let Ok(mut text) = q_text.single_mut() else {
+        return;
+    };
+    let mut lines = vec!["Controls (F1 to close)".to_string()];
+    for (i, action) in Action::ALL.into_iter().enumerate() {
+        let keys: Vec<String> = bindings
+            .keys(action)
+            .iter()
+            .map(|k| format!("{k:?}"))
+            .collect();
+        let marker = if rebind.selected == Some(action) {
+            ">"
+        } else {
+            " "
+        };
+        lines.push(format!(
+            "{marker} {}. {:<12} {}",
+            i + 1,
+            action.label(),
+            keys.join(", ")
+        ));
+    }
+    lines.push("1-3 rebind, Backspace resets to defaults".to_string());
+    lines.push("Gamepad: left stick / d-pad steers, A or RT boosts".to_string());
+    if !rebind.status.is_empty() {
+        lines.push(rebind.status.clone());
+    }
+    *text = Text::new(lines.join("\n"));
+}
+
+fn update_touch_ui(
+    touch: Res<TouchState>,
+    touches: Res<Touches>,
+    mut q_base: Query<
+        &mut Node,
+        (
+            With<TouchStickBase>,
+            Without<TouchStickKnob>,
+            Without<TouchBoostButton>,
+        ),
+    >,
+    mut q_knob: Query<&mut Node, (With<TouchStickKnob>, Without<TouchBoostButton>)>,
+    mut q_boost: Query<(&mut Node, &mut BackgroundColor), With<TouchBoostButton>>,
+) {
+    if !touch.seen {
+        return;
+    }
+    if let Ok((mut node, mut color)) = q_boost.single_mut() {
+        node.display = Display::Flex;
+        let alpha = if touch.boost.is_some() { 0.6 } else { 0.3 };
+        color.0 = Color::srgba(0.95, 0.6, 0.1, alpha);
+    }
+
+    let (Ok(mut base), Ok(mut knob)) = (q_base.single_mut(), q_knob.single_mut()) else {
+        return;
+    };
+    let Some((id, start)) = touch.stick else {
+        base.display = Display::None;
+        return;
+    };
+    base.display = Display::Flex;
+    base.left = Val::Px(start.x - TOUCH_STICK_RADIUS);
+    base.top = Val::Px(start.y - TOUCH_STICK_RADIUS);
+    // Knob follows the finger horizontally only, since only steering is analog
+    let drag = touches
+        .get_pressed(id)
+        .map_or(0.0, |t| t.position().x - start.x)
+        .clamp(-TOUCH_STICK_RADIUS, TOUCH_STICK_RADIUS);
+    knob.left = Val::Px(drag);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn load_bindings() -> Result<Option<KeyBindings>, String> {
+    if !std::path::Path::new(SETTINGS_PATH).exists() {
+        return Ok(None);
+    }
+    let json = std::fs::read_to_string(SETTINGS_PATH).map_err(|e| e.to_string())?;
+    serde_json::from_str(&json)
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_bindings(bindings: &KeyBindings) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(bindings).map_err(|e| e.to_string())?;
+    std::fs::write(SETTINGS_PATH, json).map_err(|e| e.to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Result<web_sys::Storage, String> {
+    web_sys::window()
+        .and_then(|w| w.local_storage().ok().flatten())
+        .ok_or_else(|| "localStorage unavailable".to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn load_bindings() -> Result<Option<KeyBindings>, String> {
+    let Some(json) = local_storage()?.get_item(STORAGE_KEY).ok().flatten() else {
+        return Ok(None);
+    };
+    serde_json::from_str(&json)
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn save_bindings(bindings: &KeyBindings) -> Result<(), String> {
+    let json = serde_json::to_string(bindings).map_err(|e| e.to_string())?;
+    local_storage()?
+        .set_item(STORAGE_KEY, &json)
+        .map_err(|e| format!("{e:?}"))
+}
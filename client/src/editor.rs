@@ -0,0 +1,708 @@
+// Map editor mode: renders the arena grid and lets you place, move and delete obstacles and
+// spawn points with the mouse, then saves/loads map files the server can load via MAP_FILE.
+//
+// Native: `cargo run -p client -- --editor maps/my_map.json`
+// Web:    `?editor` in the page URL (maps are kept in the browser's localStorage and
+//         downloaded as JSON on save)
+use bevy::input::mouse::AccumulatedMouseScroll;
+use bevy::pbr::prelude::StandardMaterial;
+use bevy::prelude::*;
+use shared::{MapDef, Obstacle, ObstacleShape, ShrinkZone, SpawnPoint};
+
+use crate::{spawn_map_obstacles, spawn_wire_grid, MapObstacle, SceneTag, ZoneWall};
+
+#[cfg(target_arch = "wasm32")]
+const STORAGE_KEY: &str = "hover_truck_editor_map";
+
+// Returns Some(path) when the client was launched in editor mode (path is None on web)
+pub fn editor_requested() -> Option<Option<String>> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let args: Vec<String> = std::env::args().collect();
+        let idx = args.iter().position(|a| a == "--editor")?;
+        let path = args
+            .get(idx + 1)
+            .filter(|a| !a.starts_with("--"))
+            .cloned()
+            .unwrap_or_else(|| "maps/editor.json".to_string());
+        Some(Some(path))
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        let search = web_sys::window()?.location().search().ok()?;
+        let params = web_sys::UrlSearchParams::new_with_str(&search).ok()?;
+        params.has("editor").then_some(None)
+    }
+}
+
+pub struct EditorPlugin {
+    pub path: Option<String>,
+}
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        let mut editor = EditorState {
+            map: MapDef::empty(64.0),
+            path: self.path.clone(),
+            tool: EditorTool::Select,
+            selection: None,
+            dragging: false,
+            grid_dirty: true,
+            map_dirty: true,
+            status: String::new(),
+        };
+        match load_map(editor.path.as_deref()) {
+            Ok(Some(map)) => {
+                editor.status = format!("Loaded '{}'", map.name);
+                editor.map = map;
+            }
+            Ok(None) => {
+                // Name new maps after their file
+                if let Some(stem) = editor
+                    .path
+                    .as_deref()
+                    .and_then(|p| std::path::Path::new(p).file_stem())
+                {
+                    editor.map.name = stem.to_string_lossy().into_owned();
+                }
+                editor.status = "New map".to_string();
+            }
+            Err(e) => editor.status = format!("Load failed: {e}"),
+        }
+        app.insert_resource(editor)
+            .insert_resource(EditorCamera {
+                target: Vec3::ZERO,
+                height: 90.0,
+            })
+            .add_systems(Startup, setup_editor)
+            .add_systems(
+                Update,
+                (
+                    editor_camera,
+                    editor_tool_keys,
+                    editor_mouse,
+                    editor_file_keys,
+                    rebuild_editor_scene,
+                    draw_editor_gizmos,
+                    update_editor_hud,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum EditorTool {
+    Select,
+    Pillar,
+    Wall,
+    Ramp,
+    Spawn,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Selection {
+    Obstacle(usize),
+    Spawn(usize),
+}
+
+#[derive(Resource)]
+struct EditorState {
+    map: MapDef,
+    path: Option<String>,
+    tool: EditorTool,
+    selection: Option<Selection>,
+    dragging: bool,
+    grid_dirty: bool,
+    map_dirty: bool,
+    status: String,
+}
+
+#[derive(Resource)]
+struct EditorCamera {
+    target: Vec3,
+    height: f32,
+}
+
+#[derive(Component)]
+struct EditorHud;
+
+fn setup_editor(mut commands: Commands) {
+    commands.spawn((
+        Camera::default(),
+        Camera3d::default(),
+        Transform::from_xyz(0.0, 90.0, 0.0).looking_at(Vec3::ZERO, Vec3::Z),
+        GlobalTransform::default(),
+    ));
+    commands.spawn((
+        DirectionalLight {
+            illuminance: 8000.0,
+            shadows_enabled: false,
+            ..default()
+        },
+        Transform::from_xyz(20.0, 50.0, 10.0).looking_at(Vec3::ZERO, Vec3::Y),
+    ));
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 14.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(12.0),
+            top: Val::Px(12.0),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
+        EditorHud,
+    ));
+}
+
+// Top-down camera: WASD/arrows pan, mouse wheel zooms
+fn editor_camera(
+    time: Res<Time>,
+    keys: Res<ButtonInput<KeyCode>>,
+    scroll: Res<AccumulatedMouseScroll>,
+    mut cam: ResMut<EditorCamera>,
+    mut q_cam: Query<&mut Transform, With<Camera3d>>,
+) {
+    let ctrl = keys.pressed(KeyCode::ControlLeft) || keys.pressed(KeyCode::ControlRight);
+    if !ctrl {
+        let speed = cam.height * 0.8 * time.delta_secs();
+        let mut pan = Vec3::ZERO;
+        if keys.pressed(KeyCode::KeyW) || keys.pressed(KeyCode::ArrowUp) {
+            pan.z += 1.0;
+        }
+        if keys.pressed(KeyCode::KeyS) || keys.pressed(KeyCode::ArrowDown) {
+            pan.z -= 1.0;
+        }
+        // Screen right is world -X with the camera's up vector along +Z
+        if keys.pressed(KeyCode::KeyA) || keys.pressed(KeyCode::ArrowLeft) {
+            pan.x += 1.0;
+        }
+        if keys.pressed(KeyCode::KeyD) || keys.pressed(KeyCode::ArrowRight) {
+            pan.x -= 1.0;
+        }
+        cam.target += pan * speed;
+    }
+    if scroll.delta.y != 0.0 {
+        cam.height = (cam.height * (1.0 - scroll.delta.y.signum() * 0.1)).clamp(10.0, 400.0);
+    }
+    if let Ok(mut transform) = q_cam.single_mut() {
+        let target = cam.target;
+        *transform =
+            Transform::from_translation(target + Vec3::Y * cam.height).looking_at(target, Vec3::Z);
+    }
+}
+
+fn editor_tool_keys(keys: Res<ButtonInput<KeyCode>>, mut editor: ResMut<EditorState>) {
+    let tools = [
+        (KeyCode::Digit1, EditorTool::Select),
+        (KeyCode::Digit2, EditorTool::Pillar),
+        (KeyCode::Digit3, EditorTool::Wall),
+        (KeyCode::Digit4, EditorTool::Ramp),
+        (KeyCode::Digit5, EditorTool::Spawn),
+    ];
+    for (key, tool) in tools {
+        if keys.just_pressed(key) {
+            editor.tool = tool;
+        }
+    }
+
+    // World size
+    if keys.just_pressed(KeyCode::BracketRight) {
+        editor.map.world_size += 8.0;
+        editor.grid_dirty = true;
+    }
+    if keys.just_pressed(KeyCode::BracketLeft) && editor.map.world_size > 16.0 {
+        editor.map.world_size -= 8.0;
+        editor.grid_dirty = true;
+    }
+
+    // Toggle the shrinking zone
+    if keys.just_pressed(KeyCode::KeyZ) {
+        editor.map.shrink_zone = match editor.map.shrink_zone {
+            Some(_) => None,
+            None => Some(ShrinkZone {
+                delay_secs: 120.0,
+                shrink_secs: 90.0,
+                hold_secs: 60.0,
+                min_half_size: (editor.map.world_size / 3.0).round(),
+            }),
+        };
+        editor.map_dirty = true;
+    }
+
+    let Some(selection) = editor.selection else {
+        return;
+    };
+    let delete = keys.just_pressed(KeyCode::Delete) || keys.just_pressed(KeyCode::Backspace);
+    if delete {
+        match selection {
+            Selection::Obstacle(i) => {
+                editor.map.obstacles.remove(i);
+            }
+            Selection::Spawn(i) => {
+                editor.map.spawn_points.remove(i);
+            }
+        }
+        editor.selection = None;
+        editor.map_dirty = true;
+        return;
+    }
+
+    let step = std::f32::consts::PI / 12.0;
+    let rotate = if keys.just_pressed(KeyCode::KeyQ) {
+        step
+    } else if keys.just_pressed(KeyCode::KeyE) {
+        -step
+    } else {
+        0.0
+    };
+    let scale = if keys.just_pressed(KeyCode::Equal) {
+        1.1
+    } else if keys.just_pressed(KeyCode::Minus) {
+        1.0 / 1.1
+    } else {
+        1.0
+    };
+    if rotate == 0.0 && scale == 1.0 {
+        return;
+    }
+    match selection {
+        Selection::Obstacle(i) => {
+            if let Some(obstacle) = editor.map.obstacles.get_mut(i) {
+                obstacle.rotation_y += rotate;
+                obstacle.shape = scale_shape(obstacle.shape, scale);
+            }
+        }
+        Selection::Spawn(i) => {
+            if let Some(spawn) = editor.map.spawn_points.get_mut(i) {
+                spawn.rotation_y += rotate;
+            }
+        }
+    }
+    editor.map_dirty = true;
+}
+
+fn scale_shape(shape: ObstacleShape, scale: f32) -> ObstacleShape {
+    match shape {
+        ObstacleShape::Pillar { radius, height } => ObstacleShape::Pillar {
+            radius: radius * scale,
+            height,
+        },
+        ObstacleShape::Wall {
+            width,
+            length,
+            height,
+        } => ObstacleShape::Wall {
+            width,
+            length: length * scale,
+            height,
+        },
+        ObstacleShape::Ramp {
+            width,
+            length,
+            height,
+        } => ObstacleShape::Ramp {
+            width: width * scale,
+            length: length * scale,
+            height,
+        },
+    }
+}
+
+fn cursor_ground_point(
+    q_window: &Query<&Window, With<bevy::window::PrimaryWindow>>,
+    q_cam: &Query<(&Camera, &GlobalTransform)>,
+) -> Option<Vec3> {
+    let window = q_window.single().ok()?;
+    let cursor = window.cursor_position()?;
+    let (camera, cam_transform) = q_cam.single().ok()?;
+    let ray = camera.viewport_to_world(cam_transform, cursor).ok()?;
+    let t = ray.intersect_plane(Vec3::ZERO, InfinitePlane3d::new(Vec3::Y))?;
+    Some(ray.get_point(t))
+}
+
+fn pick(map: &MapDef, x: f32, z: f32) -> Option<Selection> {
+    if let Some(i) = map.spawn_points.iter().position(|sp| {
+        let dx = sp.x - x;
+        let dz = sp.z - z;
+        dx * dx + dz * dz <= 1.5 * 1.5
+    }) {
+        return Some(Selection::Spawn(i));
+    }
+    map.obstacles
+        .iter()
+        .rposition(|o| o.overlaps_circle(x, z, 0.3) || o.ramp_height_at(x, z).is_some())
+        .map(Selection::Obstacle)
+}
+
+// Left click places with the current tool (or selects/drags with Select); right click deletes
+fn editor_mouse(
+    buttons: Res<ButtonInput<MouseButton>>,
+    q_window: Query<&Window, With<bevy::window::PrimaryWindow>>,
+    q_cam: Query<(&Camera, &GlobalTransform)>,
+    mut editor: ResMut<EditorState>,
+) {
+    let Some(point) = cursor_ground_point(&q_window, &q_cam) else {
+        return;
+    };
+    // Snap to a half-unit grid
+    let x = (point.x * 2.0).round() / 2.0;
+    let z = (point.z * 2.0).round() / 2.0;
+    let ws = editor.map.world_size;
+    let inside = x.abs() < ws && z.abs() < ws;
+
+    if buttons.just_pressed(MouseButton::Right) {
+        match pick(&editor.map, x, z) {
+            Some(Selection::Obstacle(i)) => {
+                editor.map.obstacles.remove(i);
+            }
+            Some(Selection::Spawn(i)) => {
+                editor.map.spawn_points.remove(i);
+            }
+            None => return,
+        }
+        editor.selection = None;
+        editor.map_dirty = true;
+        return;
+    }
+
+    if buttons.just_pressed(MouseButton::Left) && inside {
+        let shape = match editor.tool {
+            EditorTool::Select => {
+                editor.selection = pick(&editor.map, x, z);
+                editor.dragging = editor.selection.is_some();
+                return;
+            }
+            EditorTool::Spawn => {
+                editor.map.spawn_points.push(SpawnPoint {
+                    x,
+                    z,
+                    rotation_y: 0.0,
+                });
+                editor.selection = Some(Selection::Spawn(editor.map.spawn_points.len() - 1));
+                editor.dragging = true;
+                editor.map_dirty = true;
+                return;
+            }
+            EditorTool::Pillar => ObstacleShape::Pillar {
+                radius: 2.0,
+                height: 4.0,
+            },
+            EditorTool::Wall => ObstacleShape::Wall {
+                width: 1.0,
+                length: 12.0,
+                height: 3.0,
+            },
+            EditorTool::Ramp => ObstacleShape::Ramp {
+                width: 6.0,
+                length: 12.0,
+                height: 1.5,
+            },
+        };
+        editor.map.obstacles.push(Obstacle {
+            x,
+            z,
+            rotation_y: 0.0,
+            shape,
+        });
+        editor.selection = Some(Selection::Obstacle(editor.map.obstacles.len() - 1));
+        editor.dragging = true;
+        editor.map_dirty = true;
+        return;
+    }
+
+    if !buttons.pressed(MouseButton::Left) {
+        editor.dragging = false;
+        return;
+    }
+    if !editor.dragging || !inside {
+        return;
+    }
+    let moved = match editor.selection {
+        Some(Selection::Obstacle(i)) => editor.map.obstacles.get_mut(i).map(|o| {
+            let moved = o.x != x || o.z != z;
+            o.x = x;
+            o.z = z;
+            moved
+        }),
+        Some(Selection::Spawn(i)) => editor.map.spawn_points.get_mut(i).map(|sp| {
+            let moved = sp.x != x || sp.z != z;
+            sp.x = x;
+            sp.z = z;
+            moved
+        }),
+        None => None,
+    };
+    if moved == Some(true) {
+        editor.map_dirty = true;
+    }
+}
+
+// Ctrl+S saves, Ctrl+O reloads from disk (or localStorage on web), Ctrl+N starts a new map
+fn editor_file_keys(keys: Res<ButtonInput<KeyCode>>, mut editor: ResMut<EditorState>) {
+    let ctrl = keys.pressed(KeyCode::ControlLeft) || keys.pressed(KeyCode::ControlRight);
+    if !ctrl {
+        return;
+    }
+    if keys.just_pressed(KeyCode::KeyS) {
+        editor.status = match editor.map.validate() {
+            Err(e) => format!("Not saved: {e}"),
+            Ok(()) => match save_map(editor.path.as_deref(), &editor.map) {
+                Ok(where_to) => format!("Saved to {where_to}"),
+                Err(e) => format!("Save failed: {e}"),
+            },
+        };
+    }
+    if keys.just_pressed(KeyCode::KeyO) {
+        match load_map(editor.path.as_deref()) {
+            Ok(Some(map)) => {
+                editor.status = format!("Loaded '{}'", map.name);
+                editor.map = map;
+                editor.selection = None;
+                editor.grid_dirty = true;
+            }
+            Ok(None) => editor.status = "Nothing saved yet".to_string(),
+            Err(e) => editor.status = format!("Load failed: {e}"),
+        }
+    }
+    if keys.just_pressed(KeyCode::KeyN) {
+        let world_size = editor.map.world_size;
+        editor.map = MapDef::empty(world_size);
+        editor.selection = None;
+        editor.map_dirty = true;
+        editor.status = "New map".to_string();
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn load_map(path: Option<&str>) -> Result<Option<MapDef>, String> {
+    let Some(path) = path else {
+        return Ok(None);
+    };
+    if !std::path::Path::new(path).exists() {
+        return Ok(None);
+    }
+    MapDef::load(path).map(Some).map_err(|e| e.to_string())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_map(path: Option<&str>, map: &MapDef) -> Result<String, String> {
+    let path = path.unwrap_or("maps/editor.json");
+    if let Some(parent) = std::path::Path::new(path).parent() {
+        if !parent.as_os_str().is_empty() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+    }
+    std::fs::write(path, map.to_json_pretty()).map_err(|e| e.to_string())?;
+    Ok(path.to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Result<web_sys::Storage, String> {
+    web_sys::window()
+        .and_then(|w| w.local_storage().ok().flatten())
+        .ok_or_else(|| "localStorage unavailable".to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn load_map(_path: Option<&str>) -> Result<Option<MapDef>, String> {
+    let storage = local_storage()?;
+    let Some(json) = storage.get_item(STORAGE_KEY).ok().flatten() else {
+        return Ok(None);
+    };
+    let map: MapDef = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+    map.validate().map_err(|e| e.to_string())?;
+    Ok(Some(map))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn save_map(_path: Option<&str>, map: &MapDef) -> Result<String, String> {
+    let json = map.to_json_pretty();
+    let storage = local_storage()?;
+    storage
+        .set_item(STORAGE_KEY, &json)
+        .map_err(|e| format!("{e:?}"))?;
+    // Also download it, so the map can be given to a server as MAP_FILE
+    let file_name = map_file_name(&map.name);
+    download_json(&file_name, &json)?;
+    Ok(format!("browser storage and downloaded {file_name}"))
+}
+
+// `<map name>.json`, keeping only characters that are safe in a file name
+#[cfg(target_arch = "wasm32")]
+fn map_file_name(name: &str) -> String {
+    let stem: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if stem.is_empty() {
+        "editor.json".to_string()
+    } else {
+        format!("{stem}.json")
+    }
+}
+
+// Have the browser save `json` as a file, through a temporary object URL and link
+#[cfg(target_arch = "wasm32")]
+fn download_json(file_name: &str, json: &str) -> Result<(), String> {
+    use wasm_bindgen::{JsCast, JsValue};
+
+    let js_err = |e: JsValue| format!("{e:?}");
+    let parts = js_sys::Array::of1(&JsValue::from_str(json));
+    let options = web_sys::BlobPropertyBag::new();
+    options.set_type("application/json");
+    let blob =
+        web_sys::Blob::new_with_str_sequence_and_options(&parts, &options).map_err(js_err)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(js_err)?;
+    let document = web_sys::window()
+        .and_then(|w| w.document())
+        .ok_or_else(|| "no document to download from".to_string())?;
+    let link: web_sys::HtmlAnchorElement = document
+        .create_element("a")
+        .map_err(js_err)?
+        .dyn_into()
+        .map_err(|_| "couldn't create a download link".to_string())?;
+    link.set_href(&url);
+    link.set_download(file_name);
+    link.click();
+    web_sys::Url::revoke_object_url(&url).map_err(js_err)
+}
+
+// Respawn the grid when the world size changes and the obstacle meshes when the map changes
+fn rebuild_editor_scene(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut editor: ResMut<EditorState>,
+    q_scene: Query<Entity, With<SceneTag>>,
+    q_obstacles: Query<Entity, With<MapObstacle>>,
+) {
+    if editor.grid_dirty {
+        for entity in q_scene.iter() {
+            commands.entity(entity).despawn();
+        }
+        spawn_wire_grid(
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            editor.map.world_size,
+        );
+        editor.grid_dirty = false;
+        editor.map_dirty = true;
+    } else if editor.map_dirty {
+        for entity in q_obstacles.iter() {
+            commands.entity(entity).despawn();
+        }
+    }
+    if editor.map_dirty {
+        spawn_map_obstacles(&mut commands, &mut meshes, &mut materials, &editor.map);
+        editor.map_dirty = false;
+    }
+}
+
+fn draw_editor_gizmos(
+    mut gizmos: Gizmos,
+    editor: Res<EditorState>,
+    mut q_zone: Query<(&ZoneWall, &mut Transform)>,
+) {
+    let flat = Quat::from_rotation_x(std::f32::consts::FRAC_PI_2);
+    for (i, sp) in editor.map.spawn_points.iter().enumerate() {
+        let selected = editor.selection == Some(Selection::Spawn(i));
+        let color = if selected {
+            Color::srgb(1.0, 0.9, 0.2)
+        } else {
+            Color::srgb(0.3, 0.9, 0.4)
+        };
+        let pos = Vec3::new(sp.x, 0.1, sp.z);
+        let forward = Vec3::new(sp.rotation_y.sin(), 0.0, sp.rotation_y.cos());
+        gizmos.circle(Isometry3d::new(pos, flat), 1.0, color);
+        gizmos.arrow(pos, pos + forward * 3.0, color);
+    }
+
+    if let Some(Selection::Obstacle(i)) = editor.selection {
+        if let Some(o) = editor.map.obstacles.get(i) {
+            let pos = Vec3::new(o.x, 0.1, o.z);
+            let color = Color::srgb(1.0, 0.9, 0.2);
+            match o.shape {
+                ObstacleShape::Pillar { radius, .. } => {
+                    gizmos.circle(Isometry3d::new(pos, flat), radius + 0.3, color);
+                }
+                ObstacleShape::Wall { width, length, .. }
+                | ObstacleShape::Ramp { width, length, .. } => {
+                    let rot = Quat::from_rotation_y(o.rotation_y) * flat;
+                    gizmos.rect(
+                        Isometry3d::new(pos, rot),
+                        Vec2::new(width + 0.6, length + 0.6),
+                        color,
+                    );
+                }
+            }
+        }
+    }
+
+    // Preview the smallest zone size
+    if let Some(zone) = &editor.map.shrink_zone {
+        let half = zone.min_half_size;
+        for (wall, mut transform) in q_zone.iter_mut() {
+            if wall.axis == 0 {
+                transform.translation = Vec3::new(0.0, 2.0, wall.sign * half);
+                transform.rotation = Quat::IDENTITY;
+            } else {
+                transform.translation = Vec3::new(wall.sign * half, 2.0, 0.0);
+                transform.rotation = Quat::from_rotation_y(std::f32::consts::FRAC_PI_2);
+            }
+            transform.scale = Vec3::new(half * 2.0, 1.0, 1.0);
+        }
+    }
+}
+
+fn update_editor_hud(editor: Res<EditorState>, mut q_text: Query<&mut Text, With<EditorHud>>) {
+    if !editor.is_changed() {
+        return;
+    }
+    let Ok(mut text) = q_text.single_mut() else {
+        return;
+    };
+    let selection = match editor.selection {
+        Some(Selection::Obstacle(i)) => editor
+            .map
+            .obstacles
+            .get(i)
+            .map(|o| format!("{:?} at ({:.1}, {:.1})", o.shape, o.x, o.z))
+            .unwrap_or_default(),
+        Some(Selection::Spawn(i)) => format!("spawn point #{i}"),
+        None => "none".to_string(),
+    };
+    *text = Text::new(format!(
+        "MAP EDITOR - {}\n\
+         Tool: {:?}   [1] select [2] pillar [3] wall [4] ramp [5] spawn\n\
+         Selected: {selection}\n\
+         World size: {} ([ / ])   Obstacles: {}   Spawns: {}   Zone: {} (Z)\n\
+         LMB place/drag  RMB or Del delete  Q/E rotate  -/= resize\n\
+         WASD pan  wheel zoom  Ctrl+S save  Ctrl+O load  Ctrl+N new\n\
+         File: {}\n\
+         {}",
+        editor.map.name,
+        editor.tool,
+        editor.map.world_size,
+        editor.map.obstacles.len(),
+        editor.map.spawn_points.len(),
+        if editor.map.shrink_zone.is_some() {
+            "on"
+        } else {
+            "off"
+        },
+        editor.path.as_deref().unwrap_or("browser storage"),
+        editor.status,
+    ));
+}
@@ -6,26 +6,39 @@ use futures::{SinkExt, StreamExt};
 #[cfg(target_arch = "wasm32")]
 use js_sys::Date;
 use shared::{
-    ClientToServer, GameConfig, GameSim, PlayerId, ServerToClient, TurnInput, Vec3 as SharedVec3,
-    WorldState,
+    physics, ClientToServer, GameConfig, GameSim, MapDef, ObstacleShape, PlayerId, ServerToClient,
+    TurnInput, Vec3 as SharedVec3, WorldState,
 };
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 #[cfg(not(target_arch = "wasm32"))]
 use std::time::{Instant, SystemTime, UNIX_EPOCH};
 use uuid::Uuid;
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
+mod chat;
+mod connection;
+mod controls;
+mod editor;
+mod profile;
+mod spectate;
+
 #[derive(Resource, Default)]
 struct NetChannels {
     to_server: Option<UnboundedSender<String>>,
     from_server: Option<UnboundedReceiver<String>>,
+    // Token from the last Welcome; the connection task reconnects with it to resume our truck
+    session: Arc<Mutex<Option<String>>>,
+    status: Arc<Mutex<connection::ConnectionStatus>>, // Written by the connection task
 }
 
 #[derive(Resource)]
 struct ClientInfo {
     id: Option<Uuid>,
     world_size: f32,
+    map: Option<MapDef>,
+    spectator: bool, // Watching without a truck; `id` is only used for chat
 }
 
 #[derive(Resource, Default)]
@@ -85,8 +98,35 @@ struct LocalSim {
     sim: GameSim,
     last_server_tick: u64,
     just_respawned: bool,
+    clock: PredictionClock,
+}
+
+// Fixed-tick clock for client prediction: the predicted truck is stepped at the server's tick
+// rate and rendered between its last two ticks, so it moves the same at any frame rate
+#[derive(Default)]
+struct PredictionClock {
+    accumulator: f32,
+    prev: Option<(SharedVec3, f32)>, // Position and yaw before the latest tick
+}
+
+// Cap on ticks simulated in one frame so a long stall doesn't freeze the client catching up
+const MAX_PREDICTION_TICKS_PER_FRAME: u32 = 8;
+
+// Server corrections further than this snap the predicted truck instead of easing it over
+const TELEPORT_DISTANCE: f32 = 15.0;
+
+// Latest message from the server's admins, shown at the top of the screen for a while
+#[derive(Resource, Default)]
+struct Announcement {
+    text: String,
+    timer: Option<Timer>,
 }
 
+const ANNOUNCEMENT_SECS: f32 = 8.0;
+
+#[derive(Component)]
+struct AnnouncementText;
+
 // Test player resources (for testing with arrow keys)
 #[derive(Resource)]
 struct TestPlayerInfo {
@@ -111,6 +151,7 @@ struct TestPlayerSim {
     sim: GameSim,
     last_server_tick: u64,
     just_respawned: bool,
+    clock: PredictionClock,
 }
 
 fn main() {
@@ -131,15 +172,26 @@ fn main() {
         app.add_plugins(DefaultPlugins);
     }
 
+    // Map editor mode (--editor [path] natively, ?editor on web) runs without networking
+    if let Some(path) = editor::editor_requested() {
+        app.insert_resource(ClearColor(Color::srgb(0.05, 0.06, 0.09)))
+            .add_plugins(editor::EditorPlugin { path })
+            .run();
+        return;
+    }
+
     app.insert_resource(ClientInfo {
         id: None,
         world_size: 0.0,
+        map: None,
+        spectator: false,
     })
     .insert_resource(WorldCache::default())
     .insert_resource(NetChannels::default())
     .insert_resource(PingTracker::default())
     .insert_resource(FpsCounter::default())
     .insert_resource(LoadingState::default())
+    .insert_resource(Announcement::default())
     // Test player resources (always exist, but only populated in debug builds)
     .insert_resource(TestPlayerInfo {
         id: None,
@@ -148,17 +200,26 @@ fn main() {
     .insert_resource(TestPlayerCache::default())
     .insert_resource(TestPlayerChannels::default())
     .insert_resource(ClearColor(Color::srgb(0.05, 0.06, 0.09)))
+    .add_plugins((
+        controls::ControlsPlugin,
+        connection::ConnectionPlugin,
+        chat::ChatPlugin,
+        spectate::SpectatePlugin,
+    ))
     .add_systems(
         Startup,
         (
             setup_scene_3d,
-            net_connect,
             #[cfg(debug_assertions)]
             net_connect_test_player,
             setup_loading_screen,
+            setup_announcement,
         ),
     )
-    .add_systems(Update, (spawn_grid_once, update_loading_screen))
+    .add_systems(
+        Update,
+        (spawn_grid_once, update_loading_screen, update_announcement),
+    )
     .add_systems(
         Update,
         (
@@ -183,6 +244,7 @@ fn main() {
             interpolate_server_players,
             update_trailer_lines,
             update_minimap,
+            update_zone_walls,
         ),
     )
     .add_systems(Update, sync_world_state.after(reconcile_server_state))
@@ -315,162 +377,6 @@ fn setup_scene_3d(mut commands: Commands) {
     // Wire grid will be spawned after we get grid_size from server
 }
 
-fn net_connect(mut chans: ResMut<NetChannels>) {
-    if chans.to_server.is_some() {
-        return;
-    }
-    let (tx_out, mut rx_out) = unbounded::<String>();
-    let (tx_in, rx_in) = unbounded::<String>();
-    chans.to_server = Some(tx_out.clone());
-    chans.from_server = Some(rx_in);
-
-    #[cfg(not(target_arch = "wasm32"))]
-    let url =
-        std::env::var("SERVER_WS_URL").unwrap_or_else(|_| "ws://127.0.0.1:4001/ws".to_string());
-    #[cfg(target_arch = "wasm32")]
-    let url = {
-        let window = web_sys::window().expect("no global `window` exists");
-        let location = window.location();
-
-        // Check if custom server URL is provided via query parameter
-        let custom_url = if let Ok(url_params) =
-            web_sys::UrlSearchParams::new_with_str(location.search().unwrap_or_default().as_str())
-        {
-            url_params.get("server")
-        } else {
-            None
-        };
-
-        if let Some(server_url) = custom_url {
-            server_url
-        } else {
-            // Determine WebSocket URL based on environment
-            let hostname = location.hostname().unwrap_or_default();
-            let port = location.port().unwrap_or_default();
-            let protocol = if location.protocol().unwrap_or_default() == "https:" {
-                "wss:"
-            } else {
-                "ws:"
-            };
-
-            // For localhost development on non-standard port, connect directly to server
-            if (hostname == "127.0.0.1" || hostname == "localhost")
-                && port != "80"
-                && !port.is_empty()
-            {
-                "ws://127.0.0.1:4001/ws".to_string()
-            } else {
-                // Production or localhost on port 80: use nginx proxy (same host/port as page)
-                format!("{}//{}/ws", protocol, location.host().unwrap_or_default())
-            }
-        }
-    };
-    #[cfg(not(target_arch = "wasm32"))]
-    {
-        std::thread::spawn(move || {
-            let rt = tokio::runtime::Runtime::new().expect("tokio runtime");
-            rt.block_on(async move {
-                use tokio_tungstenite::connect_async;
-                match connect_async(&url).await {
-                    Ok((ws, _)) => {
-                        let (mut write, mut read) = ws.split();
-                        // read loop
-                        let mut tx_in2 = tx_in.clone();
-                        tokio::spawn(async move {
-                            while let Some(msg) = read.next().await {
-                                if let Ok(msg) = msg {
-                                    if msg.is_text() {
-                                        let _ = tx_in2.send(msg.into_text().unwrap()).await;
-                                    }
-                                }
-                            }
-                        });
-                        // write loop
-                        while let Some(out) = rx_out.next().await {
-                            let _ = write.send(tungstenite::Message::Text(out)).await;
-                        }
-                    }
-                    Err(e) => {
-                        log::error!("websocket connect error: {e}");
-                    }
-                }
-            });
-        });
-    }
-    #[cfg(target_arch = "wasm32")]
-    {
-        use wasm_bindgen::prelude::*;
-        use wasm_bindgen::JsCast;
-        use wasm_bindgen_futures::spawn_local;
-        use web_sys::{ErrorEvent, MessageEvent, WebSocket};
-        spawn_local(async move {
-            log::info!("Attempting to connect to WebSocket: {}", url);
-            let ws = match WebSocket::new(&url) {
-                Ok(ws) => ws,
-                Err(e) => {
-                    log::error!("Failed to create WebSocket: {:?}", e);
-                    return;
-                }
-            };
-            ws.set_binary_type(web_sys::BinaryType::Arraybuffer);
-
-            // Add onopen handler to log successful connection
-            {
-                let url_for_log = url.clone();
-                let onopen = Closure::<dyn FnMut(web_sys::Event)>::new(move |_| {
-                    log::info!("WebSocket connected to {}", url_for_log);
-                });
-                ws.set_onopen(Some(onopen.as_ref().unchecked_ref()));
-                onopen.forget();
-            }
-
-            // Add onclose handler
-            {
-                let onclose = Closure::<dyn FnMut(web_sys::Event)>::new(move |_| {
-                    log::warn!("WebSocket connection closed");
-                });
-                ws.set_onclose(Some(onclose.as_ref().unchecked_ref()));
-                onclose.forget();
-            }
-
-            {
-                let mut tx_in = tx_in.clone();
-                let onmessage = Closure::<dyn FnMut(_)>::new(move |e: MessageEvent| {
-                    if let Ok(txt) = e.data().dyn_into::<js_sys::JsString>() {
-                        let _ = tx_in.unbounded_send(String::from(txt));
-                    }
-                });
-                ws.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
-                onmessage.forget();
-            }
-            {
-                let onerror = Closure::<dyn FnMut(_)>::new(move |_e: ErrorEvent| {
-                    // ErrorEvent.message() may not be available in all browsers
-                    log::error!("WebSocket error occurred");
-                });
-                ws.set_onerror(Some(onerror.as_ref().unchecked_ref()));
-                onerror.forget();
-            }
-            // write
-            let ws_clone = ws.clone();
-            spawn_local(async move {
-                while let Some(out) = rx_out.next().await {
-                    // Check if WebSocket is still open before sending
-                    if ws_clone.ready_state() == web_sys::WebSocket::OPEN {
-                        if let Err(e) = ws_clone.send_with_str(&out) {
-                            log::error!("Failed to send WebSocket message: {:?}", e);
-                            break;
-                        }
-                    } else {
-                        log::warn!("WebSocket is not open, dropping message");
-                        break;
-                    }
-                }
-            });
-        });
-    }
-}
-
 // Test player connection (separate WebSocket) - debug builds only
 #[cfg(debug_assertions)]
 fn net_connect_test_player(mut chans: ResMut<TestPlayerChannels>) {
@@ -612,7 +518,15 @@ fn net_pump(
     mut client: ResMut<ClientInfo>,
     mut ping: ResMut<PingTracker>,
     mut loading: ResMut<LoadingState>,
+    mut local_sim: Option<ResMut<LocalSim>>,
+    mut announcement: ResMut<Announcement>,
+    mut chat: ResMut<chat::ChatState>,
+    local_profile: Res<profile::LocalProfile>,
+    q_local_player: Query<Entity, With<LocalPlayer>>,
 ) {
+    let session_slot = chans.session.clone();
+    let to_server = chans.to_server.clone();
+    let status_slot = chans.status.clone();
     if let Some(rx) = chans.from_server.as_mut() {
         let mut msgs = Vec::new();
         while let Ok(Some(m)) = rx.try_next() {
@@ -621,40 +535,87 @@ fn net_pump(
         for m in msgs {
             if let Ok(msg) = serde_json::from_str::<ServerToClient>(&m) {
                 match msg {
-                    ServerToClient::Welcome { id, world_size } => {
+                    ServerToClient::Welcome {
+                        id,
+                        world_size,
+                        map,
+                        config,
+                        session,
+                        resumed,
+                        spectator,
+                        profile,
+                    } => {
+                        if client.id.is_some() && !spectator {
+                            log::info!(
+                                "Reconnected ({})",
+                                if resumed {
+                                    "resumed our truck"
+                                } else {
+                                    "new truck"
+                                }
+                            );
+                        }
+                        // A new truck after a reconnect: drop the old one, it spawns again from state
+                        if client.id != Some(id) {
+                            for entity in q_local_player.iter() {
+                                commands.entity(entity).despawn();
+                            }
+                        }
+                        // Spectators have nothing to resume
+                        *session_slot.lock().unwrap() = (!spectator).then_some(session);
                         client.id = Some(id);
+                        client.spectator = spectator;
                         client.world_size = world_size;
+                        client.map = Some(map.clone());
                         cache.state = None;
                         loading.welcome_received = true;
                         // Initialize local simulation
                         let mut local_sim = LocalSim {
-                            sim: GameSim::new(GameConfig {
-                                world_size,
-                                player_speed: 6.0,
-                                turn_speed: 2.5,
-                                initial_length: 3,
-                                item_spawn_every_ticks: 20,
-                            }),
+                            // Predict with the server's tuning so the two don't drift
+                            sim: GameSim::with_map(config, map),
                             last_server_tick: 0,
                             just_respawned: false,
+                            clock: PredictionClock::default(),
                         };
-                        // Add local player to sim
-                        local_sim.sim.state.players.insert(
-                            id,
-                            shared::PlayerState {
+                        // Add local player to sim; spectators only render the others
+                        if !spectator {
+                            local_sim.sim.state.players.insert(
                                 id,
-                                position: SharedVec3 {
-                                    x: 0.0,
-                                    y: 0.5,
-                                    z: 0.0,
+                                shared::PlayerState {
+                                    id,
+                                    position: SharedVec3 {
+                                        x: 0.0,
+                                        y: 0.5,
+                                        z: 0.0,
+                                    },
+                                    rotation_y: 0.0,
+                                    trailer: std::collections::VecDeque::new(),
+                                    trailer_headings: std::collections::VecDeque::new(),
+                                    alive: true,
+                                    boost_meter: 1.0,
                                 },
-                                rotation_y: 0.0,
-                                trailer: std::collections::VecDeque::new(),
-                                alive: true,
-                                boost_meter: 1.0,
-                            },
-                        );
+                            );
+                        }
                         commands.insert_resource(local_sim);
+                        if let Some(profile) = profile {
+                            // Save the name and colour we picked to the profile
+                            if let (Some(tx), Some(hello)) = (&to_server, local_profile.hello()) {
+                                let _ = tx.unbounded_send(serde_json::to_string(&hello).unwrap());
+                            }
+                            if !resumed {
+                                let stats = profile.stats;
+                                chat.push(
+                                    format!(
+                                        "Profile: {} games, {} kills, {} deaths, longest trailer {}",
+                                        stats.games,
+                                        stats.kills,
+                                        stats.deaths,
+                                        stats.longest_trailer
+                                    ),
+                                    chat::LineKind::Notice,
+                                );
+                            }
+                        }
                     }
                     ServerToClient::State(world) => {
                         if !loading.first_state_received {
@@ -675,6 +636,35 @@ fn net_pump(
                         }
                     }
                     ServerToClient::YouDied => {}
+                    ServerToClient::ServerMessage(text) => {
+                        log::info!("Server message: {text}");
+                        chat.push(format!("[Server] {text}"), chat::LineKind::Server);
+                        announcement.text = text;
+                        announcement.timer =
+                            Some(Timer::from_seconds(ANNOUNCEMENT_SECS, TimerMode::Once));
+                    }
+                    ServerToClient::Chat { from, text } => {
+                        let speaker = chat::speaker(from, client.id, cache.state.as_ref());
+                        let line = format!("{speaker}: {text}");
+                        chat.push(line, chat::LineKind::Chat);
+                    }
+                    ServerToClient::Emote { from, emote } => chat.show_emote(from, emote),
+                    ServerToClient::ChatRejected(reason) => {
+                        chat.push(format!("Not sent: {reason}"), chat::LineKind::Notice);
+                    }
+                    ServerToClient::ConfigChanged(config) => {
+                        log::info!("Server reloaded its gameplay settings");
+                        // Keep predicting with the same tuning the server now steps with
+                        if let Some(local_sim) = local_sim.as_mut() {
+                            local_sim.sim.set_config(config);
+                        }
+                    }
+                    ServerToClient::ServerShutdown { reason, seconds } => {
+                        log::warn!("Server shutting down in {seconds}s: {reason}");
+                        // Shown until the connection task reports the disconnect
+                        *status_slot.lock().unwrap() =
+                            connection::ConnectionStatus::ShuttingDown { reason, seconds };
+                    }
                 }
             }
         }
@@ -697,21 +687,23 @@ fn net_pump_test_player(
         for m in msgs {
             if let Ok(msg) = serde_json::from_str::<ServerToClient>(&m) {
                 match msg {
-                    ServerToClient::Welcome { id, world_size } => {
+                    ServerToClient::Welcome {
+                        id,
+                        world_size,
+                        map,
+                        config,
+                        ..
+                    } => {
                         test_client.id = Some(id);
                         test_client.world_size = world_size;
                         cache.state = None;
                         // Initialize test player simulation
                         let mut test_sim = TestPlayerSim {
-                            sim: GameSim::new(GameConfig {
-                                world_size,
-                                player_speed: 6.0,
-                                turn_speed: 2.5,
-                                initial_length: 3,
-                                item_spawn_every_ticks: 20,
-                            }),
+                            // Predict with the server's tuning so the two don't drift
+                            sim: GameSim::with_map(config, map),
                             last_server_tick: 0,
                             just_respawned: false,
+                            clock: PredictionClock::default(),
                         };
                         // Add test player to sim
                         test_sim.sim.state.players.insert(
@@ -725,6 +717,7 @@ fn net_pump_test_player(
                                 },
                                 rotation_y: 0.0,
                                 trailer: std::collections::VecDeque::new(),
+                                trailer_headings: std::collections::VecDeque::new(),
                                 alive: true,
                                 boost_meter: 1.0,
                             },
@@ -744,13 +737,13 @@ fn net_pump_test_player(
 // Send player input to server and apply locally immediately (client-side prediction)
 fn send_player_input(
     time: Res<Time>,
-    keys: Res<ButtonInput<KeyCode>>,
+    controls: Res<controls::PlayerControls>,
     client: Res<ClientInfo>,
     chans: ResMut<NetChannels>,
     mut local_sim: Option<ResMut<LocalSim>>,
     mut timer: Local<Option<Timer>>,
 ) {
-    if client.id.is_none() {
+    if client.id.is_none() || client.spectator {
         return;
     }
     if chans.to_server.is_none() {
@@ -770,27 +763,30 @@ fn send_player_input(
         return;
     }
 
-    // Determine turn input from keys (A/D only, arrow keys are for test player)
-    let turn = if keys.pressed(KeyCode::KeyA) {
+    // Keyboard, gamepad and touch combined; `turn` is the digital fallback for older servers
+    let steer = controls.steer;
+    let boost = controls.boost;
+    let turn = if steer > 0.0 {
         TurnInput::Left
-    } else if keys.pressed(KeyCode::KeyD) {
+    } else if steer < 0.0 {
         TurnInput::Right
     } else {
         TurnInput::Straight
     };
 
-    // Check for boost input (W key)
-    let boost = keys.pressed(KeyCode::KeyW);
-
     // Apply input locally immediately (client-side prediction)
     if let Some(my_id) = client.id {
-        sim.sim.submit_input(my_id, turn);
+        sim.sim.submit_steer(my_id, steer);
         sim.sim.submit_boost(my_id, boost);
     }
 
     // Send input to server
     if let Some(tx) = &chans.to_server {
-        let msg = ClientToServer::Input { turn, boost };
+        let msg = ClientToServer::Input {
+            turn,
+            boost,
+            steer: Some(steer),
+        };
         if let Ok(json) = serde_json::to_string(&msg) {
             let _ = tx.unbounded_send(json);
         }
@@ -847,184 +843,66 @@ fn send_test_player_input(
 
     // Send input to server
     if let Some(tx) = &chans.to_server {
-        let msg = ClientToServer::Input { turn, boost };
+        let msg = ClientToServer::Input {
+            turn,
+            boost,
+            steer: None,
+        };
         if let Ok(json) = serde_json::to_string(&msg) {
             let _ = tx.unbounded_send(json);
         }
     }
 }
 
-// Helper function to update trailer with actual cart positions (matching server logic)
-fn update_trailer_positions(player: &mut shared::PlayerState) {
-    let gap = 0.8;
-    let player_back_offset = 0.9;
-    let cart_front_offset = 0.7;
-    let cart_back_offset = 0.7;
-    let hitch_length = gap + cart_front_offset;
-
-    let player_forward = shared::Vec3 {
-        x: player.rotation_y.sin(),
-        y: 0.0,
-        z: player.rotation_y.cos(),
-    };
-
-    // Calculate new cart positions based on current player state
-    let mut new_trailer = std::collections::VecDeque::new();
-    new_trailer.push_back(player.position); // First element is always player position
-
-    let trailer_length = player.trailer.len();
-    if trailer_length > 1 {
-        // Get previous cart positions for direction calculation
-        let mut prev_cart_pos: Option<shared::Vec3> = None;
-        let mut prev_cart_forward: Option<shared::Vec3> = None;
-
-        // Use previous trailer positions to get direction, but recalculate actual positions
-        let mut old_trailer_iter = player.trailer.iter().skip(1);
-
-        for order in 0..(trailer_length - 1) {
-            let old_pos = old_trailer_iter.next();
-
-            let (cart_pos, cart_forward) = if order == 0 {
-                // First cart: attached to player
-                let hitch_point = shared::Vec3 {
-                    x: player.position.x - player_forward.x * player_back_offset,
-                    y: 0.5,
-                    z: player.position.z - player_forward.z * player_back_offset,
-                };
-
-                // Use old position to determine direction if available
-                if let Some(&old_cart_pos) = old_pos {
-                    let to_hitch = shared::Vec3 {
-                        x: hitch_point.x - old_cart_pos.x,
-                        y: 0.0,
-                        z: hitch_point.z - old_cart_pos.z,
-                    };
-                    let to_hitch_dist = (to_hitch.x * to_hitch.x + to_hitch.z * to_hitch.z).sqrt();
-
-                    if to_hitch_dist > 0.001 {
-                        let to_hitch_dir = shared::Vec3 {
-                            x: to_hitch.x / to_hitch_dist,
-                            y: 0.0,
-                            z: to_hitch.z / to_hitch_dist,
-                        };
-                        let cart_pos = shared::Vec3 {
-                            x: hitch_point.x - to_hitch_dir.x * hitch_length,
-                            y: 0.5,
-                            z: hitch_point.z - to_hitch_dir.z * hitch_length,
-                        };
-                        (cart_pos, to_hitch_dir)
-                    } else {
-                        let backward = shared::Vec3 {
-                            x: -player_forward.x,
-                            y: 0.0,
-                            z: -player_forward.z,
-                        };
-                        let cart_pos = shared::Vec3 {
-                            x: hitch_point.x + backward.x * hitch_length,
-                            y: 0.5,
-                            z: hitch_point.z + backward.z * hitch_length,
-                        };
-                        (cart_pos, player_forward)
-                    }
-                } else {
-                    let backward = shared::Vec3 {
-                        x: -player_forward.x,
-                        y: 0.0,
-                        z: -player_forward.z,
-                    };
-                    let cart_pos = shared::Vec3 {
-                        x: hitch_point.x + backward.x * hitch_length,
-                        y: 0.5,
-                        z: hitch_point.z + backward.z * hitch_length,
-                    };
-                    (cart_pos, player_forward)
-                }
-            } else {
-                // Subsequent carts
-                if let (Some(prev_pos), Some(prev_fwd)) = (prev_cart_pos, prev_cart_forward) {
-                    let hitch_point = shared::Vec3 {
-                        x: prev_pos.x - prev_fwd.x * cart_back_offset,
-                        y: 0.5,
-                        z: prev_pos.z - prev_fwd.z * cart_back_offset,
-                    };
-
-                    if let Some(&old_cart_pos) = old_pos {
-                        let to_hitch = shared::Vec3 {
-                            x: hitch_point.x - old_cart_pos.x,
-                            y: 0.0,
-                            z: hitch_point.z - old_cart_pos.z,
-                        };
-                        let to_hitch_dist =
-                            (to_hitch.x * to_hitch.x + to_hitch.z * to_hitch.z).sqrt();
-
-                        if to_hitch_dist > 0.001 {
-                            let to_hitch_dir = shared::Vec3 {
-                                x: to_hitch.x / to_hitch_dist,
-                                y: 0.0,
-                                z: to_hitch.z / to_hitch_dist,
-                            };
-                            let cart_pos = shared::Vec3 {
-                                x: hitch_point.x - to_hitch_dir.x * hitch_length,
-                                y: 0.5,
-                                z: hitch_point.z - to_hitch_dir.z * hitch_length,
-                            };
-                            (cart_pos, to_hitch_dir)
-                        } else {
-                            let backward = shared::Vec3 {
-                                x: -prev_fwd.x,
-                                y: 0.0,
-                                z: -prev_fwd.z,
-                            };
-                            let cart_pos = shared::Vec3 {
-                                x: hitch_point.x + backward.x * hitch_length,
-                                y: 0.5,
-                                z: hitch_point.z + backward.z * hitch_length,
-                            };
-                            (cart_pos, prev_fwd)
-                        }
-                    } else {
-                        let backward = shared::Vec3 {
-                            x: -prev_fwd.x,
-                            y: 0.0,
-                            z: -prev_fwd.z,
-                        };
-                        let cart_pos = shared::Vec3 {
-                            x: hitch_point.x + backward.x * hitch_length,
-                            y: 0.5,
-                            z: hitch_point.z + backward.z * hitch_length,
-                        };
-                        (cart_pos, prev_fwd)
-                    }
-                } else {
-                    // Fallback
-                    let backward = shared::Vec3 {
-                        x: -player_forward.x,
-                        y: 0.0,
-                        z: -player_forward.z,
-                    };
-                    let cart_pos = shared::Vec3 {
-                        x: player.position.x + backward.x * hitch_length * (order as f32 + 1.0),
-                        y: 0.5,
-                        z: player.position.z + backward.z * hitch_length * (order as f32 + 1.0),
-                    };
-                    (cart_pos, player_forward)
-                }
-            };
-
-            new_trailer.push_back(cart_pos);
-            prev_cart_pos = Some(cart_pos);
-            prev_cart_forward = Some(cart_forward);
+// Run as many fixed prediction ticks as this frame's time covers for one truck in `game`
+// and return the pose to render, interpolated between the last two ticks. None if the
+// truck is missing or dead.
+fn advance_prediction(
+    clock: &mut PredictionClock,
+    game: &mut GameSim,
+    id: PlayerId,
+    steer: f32,
+    boost: bool,
+    frame_dt: f32,
+) -> Option<(Vec3, Quat)> {
+    let GameSim {
+        state, cfg, map, ..
+    } = game;
+    let zone_half_size = state.zone_half_size;
+    let player = state.players.get_mut(&id).filter(|p| p.alive)?;
+
+    let dt = cfg.dt();
+    clock.accumulator += frame_dt;
+    let mut ticks = 0;
+    while clock.accumulator >= dt {
+        clock.prev = Some((player.position, player.rotation_y));
+        clock.accumulator -= dt;
+        // Same movement and lethal checks as the server; the server still has the final say
+        // on deaths, and reconcile_server_state revives us if it disagrees
+        physics::advance_player(player, steer, boost, cfg, dt);
+        if physics::resolve_environment(player, map, cfg.world_size, zone_half_size).is_some() {
+            player.alive = false;
+            break;
+        }
+        ticks += 1;
+        if ticks >= MAX_PREDICTION_TICKS_PER_FRAME {
+            clock.accumulator = 0.0;
+            break;
         }
     }
 
-    // Update trailer with new positions
-    player.trailer = new_trailer;
+    let alpha = (clock.accumulator / dt).clamp(0.0, 1.0);
+    let (prev_pos, prev_rot) = clock.prev.unwrap_or((player.position, player.rotation_y));
+    let pos = shared_to_bevy_vec3(prev_pos).lerp(shared_to_bevy_vec3(player.position), alpha);
+    let rot =
+        Quat::from_rotation_y(prev_rot).slerp(Quat::from_rotation_y(player.rotation_y), alpha);
+    Some((pos, rot))
 }
 
-// Move local player every frame (client-side prediction)
+// Move local player at the fixed tick rate (client-side prediction)
 fn local_player_move(
     time: Res<Time>,
-    keys: Res<ButtonInput<KeyCode>>,
+    controls: Res<controls::PlayerControls>,
     client: Res<ClientInfo>,
     mut local_sim: Option<ResMut<LocalSim>>,
     mut q_local_player: Query<&mut Transform, (With<LocalPlayer>, Without<Camera>)>,
@@ -1039,65 +917,19 @@ fn local_player_move(
     // Skip transform update if we just respawned (sync_world_state will handle it)
     let just_respawned = sim.just_respawned;
 
-    let dt = time.delta_secs();
-    let world_size = sim.sim.cfg.world_size;
-    let turn_speed = sim.sim.cfg.turn_speed;
-    let player_speed = sim.sim.cfg.player_speed;
-
-    // Get local player from sim
-    let Some(player) = sim.sim.state.players.get_mut(&my_id) else {
+    let LocalSim {
+        sim: game, clock, ..
+    } = &mut *sim;
+    let Some((pos, rot)) = advance_prediction(
+        clock,
+        game,
+        my_id,
+        controls.steer,
+        controls.boost,
+        time.delta_secs(),
+    ) else {
         return;
     };
-    if !player.alive {
-        return;
-    }
-
-    // Apply turn input every frame based on current key state (smooth turning)
-    // A/D only, arrow keys are for test player
-    if keys.pressed(KeyCode::KeyA) {
-        player.rotation_y += turn_speed * dt;
-    } else if keys.pressed(KeyCode::KeyD) {
-        player.rotation_y -= turn_speed * dt;
-    }
-
-    // Handle boost input and update boost meter (same logic as server)
-    let boost_pressed = keys.pressed(KeyCode::KeyW);
-    let boost_active = boost_pressed && player.boost_meter > 0.0;
-
-    if boost_active {
-        // Deplete boost meter while boosting (depletes in 2 seconds at full speed)
-        let deplete_rate = 1.0 / 2.0; // Deplete full meter in 2 seconds
-        player.boost_meter -= deplete_rate * dt;
-        if player.boost_meter < 0.0 {
-            player.boost_meter = 0.0;
-        }
-    } else {
-        // Regenerate boost meter slowly when not boosting (regenerates in 5 seconds)
-        let regen_rate = 1.0 / 5.0; // Regenerate full meter in 5 seconds
-        player.boost_meter += regen_rate * dt;
-        if player.boost_meter > 1.0 {
-            player.boost_meter = 1.0;
-        }
-    }
-
-    // Apply movement (same logic as server) with boost multiplier
-    let speed_multiplier = if boost_active { 2.0 } else { 1.0 };
-    let forward_x = player.rotation_y.sin();
-    let forward_z = player.rotation_y.cos();
-    player.position.x += forward_x * player_speed * speed_multiplier * dt;
-    player.position.z += forward_z * player_speed * speed_multiplier * dt;
-
-    // Clamp position to world bounds (walls will kill on server, but prevent visual glitches)
-    let player_radius = 0.5;
-    player.position.x = player
-        .position
-        .x
-        .clamp(-world_size + player_radius, world_size - player_radius);
-    player.position.z = player
-        .position
-        .z
-        .clamp(-world_size + player_radius, world_size - player_radius);
-    player.position.y = 0.5;
 
     // Don't update trailer positions here - let the server be authoritative
     // The server will update trailer positions, and we sync from it in reconcile_server_state
@@ -1107,15 +939,13 @@ fn local_player_move(
     // (sync_world_state will handle the instant update)
     if !just_respawned {
         if let Ok(mut transform) = q_local_player.single_mut() {
-            let pos = shared_to_bevy_vec3(player.position);
-            let rot = Quat::from_rotation_y(player.rotation_y);
             transform.translation = pos;
             transform.rotation = rot;
         }
     }
 }
 
-// Move test player every frame (client-side prediction) - debug builds only
+// Move test player at the fixed tick rate (client-side prediction) - debug builds only
 #[cfg(debug_assertions)]
 fn test_player_move(
     time: Res<Time>,
@@ -1137,75 +967,35 @@ fn test_player_move(
     // Skip transform update if we just respawned (sync_world_state will handle it)
     let just_respawned = sim.just_respawned;
 
-    let dt = time.delta_secs();
-    let world_size = sim.sim.cfg.world_size;
-    let turn_speed = sim.sim.cfg.turn_speed;
-    let player_speed = sim.sim.cfg.player_speed;
-
-    // Get test player from sim
-    let Some(player) = sim.sim.state.players.get_mut(&test_id) else {
-        return;
-    };
-    if !player.alive {
-        return;
-    }
-
-    // Apply turn input every frame based on arrow keys (smooth turning)
-    if keys.pressed(KeyCode::ArrowLeft) {
-        player.rotation_y += turn_speed * dt;
+    // Arrow keys only
+    let turn = if keys.pressed(KeyCode::ArrowLeft) {
+        TurnInput::Left
     } else if keys.pressed(KeyCode::ArrowRight) {
-        player.rotation_y -= turn_speed * dt;
-    }
-
-    // Handle boost input and update boost meter (same logic as server)
-    let boost_pressed = keys.pressed(KeyCode::KeyW);
-    let boost_active = boost_pressed && player.boost_meter > 0.0;
-
-    if boost_active {
-        // Deplete boost meter while boosting (depletes in 2 seconds at full speed)
-        let deplete_rate = 1.0 / 2.0; // Deplete full meter in 2 seconds
-        player.boost_meter -= deplete_rate * dt;
-        if player.boost_meter < 0.0 {
-            player.boost_meter = 0.0;
-        }
+        TurnInput::Right
     } else {
-        // Regenerate boost meter slowly when not boosting (regenerates in 5 seconds)
-        let regen_rate = 1.0 / 5.0; // Regenerate full meter in 5 seconds
-        player.boost_meter += regen_rate * dt;
-        if player.boost_meter > 1.0 {
-            player.boost_meter = 1.0;
-        }
-    }
-
-    // Apply movement (same logic as server) with boost multiplier
-    let speed_multiplier = if boost_active { 2.0 } else { 1.0 };
-    let forward_x = player.rotation_y.sin();
-    let forward_z = player.rotation_y.cos();
-    player.position.x += forward_x * player_speed * speed_multiplier * dt;
-    player.position.z += forward_z * player_speed * speed_multiplier * dt;
-
-    // Clamp position to world bounds (walls will kill on server, but prevent visual glitches)
-    let player_radius = 0.5;
-    player.position.x = player
-        .position
-        .x
-        .clamp(-world_size + player_radius, world_size - player_radius);
-    player.position.z = player
-        .position
-        .z
-        .clamp(-world_size + player_radius, world_size - player_radius);
-    player.position.y = 0.5;
+        TurnInput::Straight
+    };
+    let boost_pressed = keys.pressed(KeyCode::KeyW);
+    let TestPlayerSim {
+        sim: game, clock, ..
+    } = &mut *sim;
+    let Some((pos, rot)) = advance_prediction(
+        clock,
+        game,
+        test_id,
+        turn.steer(),
+        boost_pressed,
+        time.delta_secs(),
+    ) else {
+        return;
+    };
 
     // Don't update trailer positions here - let the server be authoritative
-    // The server will update trailer positions, and we sync from it in reconcile_server_state
-    // This prevents desync issues where client has different trailer length than server
 
     // Update visual transform immediately, but skip if we just respawned
     // (sync_world_state will handle the instant update)
     if !just_respawned {
         if let Ok(mut transform) = q_test_player.single_mut() {
-            let pos = shared_to_bevy_vec3(player.position);
-            let rot = Quat::from_rotation_y(player.rotation_y);
             transform.translation = pos;
             transform.rotation = rot;
         }
@@ -1235,13 +1025,6 @@ fn update_truck_trailers(
 
     let dt = time.delta_secs();
 
-    // Physics parameters for truck trailer behavior
-    let gap = 0.8;
-    let player_back_offset = 0.9; // Distance from player center to player back
-    let cart_front_offset = 0.7; // Distance from cart center to cart front
-    let cart_back_offset = 0.7; // Distance from cart center to cart back
-    let hitch_length = gap + cart_front_offset; // Total distance from attachment point to cart center
-
     // Build a map of player transforms (rendered positions)
     let mut player_transforms: HashMap<PlayerId, Transform> = HashMap::new();
 
@@ -1263,21 +1046,15 @@ fn update_truck_trailers(
         player_transforms.insert(server_player.id, *transform);
     }
 
-    // Group carts by player and sort by order
-    let mut carts_by_player: std::collections::HashMap<_, Vec<_>> =
-        std::collections::HashMap::new();
-    for (cart, transform) in q_carts.iter() {
-        carts_by_player
-            .entry(cart.player_id)
-            .or_insert_with(Vec::new)
-            .push((cart.order, transform.translation, transform.rotation));
-    }
+    // Players that currently have cart entities
+    let players_with_carts: std::collections::HashSet<PlayerId> =
+        q_carts.iter().map(|(cart, _)| cart.player_id).collect();
 
-    // Calculate target positions for all carts (process in order to build chain)
+    // Calculate target poses for all carts
     let mut cart_targets: std::collections::HashMap<(PlayerId, usize), (Vec3, Quat)> =
         std::collections::HashMap::new();
 
-    for (player_id, cart_list) in carts_by_player.iter() {
+    for player_id in players_with_carts.iter() {
         // Get player transform (rendered position)
         let Some(player_transform) = player_transforms.get(player_id) else {
             continue;
@@ -1306,82 +1083,29 @@ fn update_truck_trailers(
                 continue;
             }
 
-            // Sort by order to process sequentially
-            let mut sorted_carts: Vec<_> = cart_list.iter().collect();
-            sorted_carts.sort_by_key(|(order, _, _)| *order);
-
-            // Process carts in order, building the chain with truck trailer physics
-            for (order, cart_pos, cart_rot) in sorted_carts {
-                let (target_world_pos, target_rot) = if *order == 1 {
-                    // First trailer: attached to truck (player)
-                    // Calculate hitch point on the truck (back of player)
-                    let player_forward = player_transform.rotation * Vec3::Z;
-                    let hitch_point =
-                        player_transform.translation - player_forward * player_back_offset;
-
-                    // Direction from current cart position to hitch point
-                    let to_hitch = hitch_point - *cart_pos;
-                    let to_hitch_dist = to_hitch.length();
-
-                    if to_hitch_dist > 0.001 {
-                        let to_hitch_dir = to_hitch / to_hitch_dist;
-
-                        // Target position: hitch point minus hitch_length along the direction
-                        // This creates a natural swinging motion
-                        let target_pos = hitch_point - to_hitch_dir * hitch_length;
-                        let target_pos = Vec3::new(target_pos.x, 0.4, target_pos.z);
-
-                        // Rotation: align with the direction from cart to hitch (trailer follows path)
-                        let target_rotation = Quat::from_rotation_arc(Vec3::Z, to_hitch_dir);
-
-                        (target_pos, target_rotation)
-                    } else {
-                        // Fallback: straight line behind player
-                        let target_pos = hitch_point - player_forward * hitch_length;
-                        let target_pos = Vec3::new(target_pos.x, 0.4, target_pos.z);
-                        (target_pos, player_transform.rotation)
-                    }
-                } else {
-                    // Subsequent trailers: attached to previous trailer
-                    let prev_cart_order = *order - 1;
-                    let prev_cart_key = (*player_id, prev_cart_order);
-
-                    if let Some((prev_cart_target_pos, prev_cart_target_rot)) =
-                        cart_targets.get(&prev_cart_key)
-                    {
-                        // Calculate hitch point on previous trailer (back of previous trailer)
-                        let prev_forward = *prev_cart_target_rot * Vec3::Z;
-                        let hitch_point = *prev_cart_target_pos - prev_forward * cart_back_offset;
-
-                        // Direction from current cart position to hitch point
-                        let to_hitch = hitch_point - *cart_pos;
-                        let to_hitch_dist = to_hitch.length();
-
-                        if to_hitch_dist > 0.001 {
-                            let to_hitch_dir = to_hitch / to_hitch_dist;
-
-                            // Target position: hitch point minus hitch_length along the direction
-                            let target_pos = hitch_point - to_hitch_dir * hitch_length;
-                            let target_pos = Vec3::new(target_pos.x, 0.4, target_pos.z);
-
-                            // Rotation: align with the direction from cart to hitch
-                            let target_rotation = Quat::from_rotation_arc(Vec3::Z, to_hitch_dir);
-
-                            (target_pos, target_rotation)
-                        } else {
-                            // Fallback: straight line behind previous trailer
-                            let target_pos = hitch_point - prev_forward * hitch_length;
-                            let target_pos = Vec3::new(target_pos.x, 0.4, target_pos.z);
-                            (target_pos, *prev_cart_target_rot)
-                        }
-                    } else {
-                        // Fallback: use current transform if previous cart not found
-                        (*cart_pos, *cart_rot)
-                    }
-                };
-
-                // Store the target for next cart to use and for applying later
-                cart_targets.insert((*player_id, *order), (target_world_pos, target_rot));
+            // Chain the carts off the rendered truck using the server's cart headings, so they
+            // stay attached to the predicted/interpolated truck but articulate like the sim
+            let cfg = &sim.sim.cfg;
+            let player_forward = player_transform.rotation * Vec3::Z;
+            let mut hitch_point =
+                player_transform.translation - player_forward * cfg.truck_hitch_offset;
+            let mut heading = player_state.rotation_y;
+            for order in 1..player_state.trailer.len() {
+                heading = player_state
+                    .trailer_headings
+                    .get(order)
+                    .copied()
+                    .unwrap_or(heading);
+                let rotation = Quat::from_rotation_y(heading);
+                let forward = rotation * Vec3::Z;
+                let center = hitch_point - forward * cfg.hitch_length();
+                // Carts sit a little lower than they hover in the sim, riding up ramps with it
+                let y = 0.4 + sim.sim.map.ground_height(center.x, center.z);
+                cart_targets.insert(
+                    (*player_id, order),
+                    (Vec3::new(center.x, y, center.z), rotation),
+                );
+                hitch_point = center - forward * (cfg.cart_length * 0.5);
             }
         }
     }
@@ -1431,6 +1155,7 @@ fn reconcile_server_state(
     // Update all players and items from server
     sim.sim.state.players = world.players.clone();
     sim.sim.state.items = world.items.clone();
+    sim.sim.state.zone_half_size = world.zone_half_size;
 
     // Reconcile local player: smoothly correct towards server position
     let server_player_opt = sim.sim.state.players.get(&my_id).cloned();
@@ -1438,11 +1163,18 @@ fn reconcile_server_state(
         // If player was dead and is now alive, use server state directly (respawn)
         let was_dead = my_local_player.as_ref().map_or(false, |p| !p.alive);
         let is_now_alive = server_player.alive;
+        // Moved further than any correction should (e.g. an admin restarted the match)
+        let teleported = my_local_player.as_ref().map_or(false, |p| {
+            let dx = p.position.x - server_player.position.x;
+            let dz = p.position.z - server_player.position.z;
+            dx * dx + dz * dz > TELEPORT_DISTANCE * TELEPORT_DISTANCE
+        });
 
-        if was_dead && is_now_alive {
+        if (was_dead && is_now_alive) || teleported {
             // Player respawned - use server state directly
             sim.sim.state.players.insert(my_id, server_player);
             sim.just_respawned = true; // Flag for instant transform update
+            sim.clock.prev = None; // Don't interpolate from the death position
         } else {
             sim.just_respawned = false;
             if let Some(mut local_player) = my_local_player {
@@ -1514,6 +1246,7 @@ fn reconcile_test_player_state(
     // Update all players and items from server
     sim.sim.state.players = world.players.clone();
     sim.sim.state.items = world.items.clone();
+    sim.sim.state.zone_half_size = world.zone_half_size;
 
     // Reconcile test player: smoothly correct towards server position
     let server_player_opt = sim.sim.state.players.get(&test_id).cloned();
@@ -1526,6 +1259,7 @@ fn reconcile_test_player_state(
             // Player respawned - use server state directly
             sim.sim.state.players.insert(test_id, server_player);
             sim.just_respawned = true; // Flag for instant transform update
+            sim.clock.prev = None; // Don't interpolate from the death position
         } else {
             sim.just_respawned = false;
             if let Some(mut local_player) = my_test_player {
@@ -1621,8 +1355,12 @@ fn sync_world_state(
     }
 
     // Spawn/update players - rectangular hover truck shape (longer front-to-back)
-    // Width: 0.8, Height: 0.8, Length: 1.8 (front-to-back)
-    let player_mesh = meshes.add(Cuboid::new(0.8, 0.8, 1.8));
+    // Sized from the game config so it matches the collision box
+    let player_mesh = meshes.add(Cuboid::new(
+        sim.sim.cfg.truck_width,
+        0.8,
+        sim.sim.cfg.truck_length,
+    ));
     for (player_id, player_state) in world.players.iter() {
         let is_me = *player_id == my_id;
         #[cfg(debug_assertions)]
@@ -1798,8 +1536,12 @@ fn sync_world_state(
     }
 
     // Spawn/update truck trailers (only spawn/despawn, positions updated by update_truck_trailers)
-    // Cart shape: rectangular like player but slightly smaller (Width: 0.7, Height: 0.7, Length: 1.4)
-    let cart_mesh = meshes.add(Cuboid::new(0.7, 0.7, 1.4));
+    // Cart shape: rectangular like player but slightly smaller, sized from the game config
+    let cart_mesh = meshes.add(Cuboid::new(
+        sim.sim.cfg.cart_width,
+        0.7,
+        sim.sim.cfg.cart_length,
+    ));
 
     // Helper function to generate a random color from cart ID (deterministic)
     let cart_color = |player_id: &Uuid, order: usize| -> Color {
@@ -1837,6 +1579,11 @@ fn sync_world_state(
 
         for (order, cart_pos) in player_state.trailer.iter().enumerate().skip(1) {
             let key = (*player_id, order);
+            let heading = player_state
+                .trailer_headings
+                .get(order)
+                .copied()
+                .unwrap_or(player_state.rotation_y);
 
             if existing_carts.remove(&key).is_none() {
                 // Generate unique color for this cart
@@ -1849,7 +1596,8 @@ fn sync_world_state(
                 commands.spawn((
                     Mesh3d(cart_mesh.clone()),
                     MeshMaterial3d(cart_mat),
-                    Transform::from_translation(spawn_pos),
+                    Transform::from_translation(spawn_pos)
+                        .with_rotation(Quat::from_rotation_y(heading)),
                     GlobalTransform::default(),
                     Visibility::default(),
                     InheritedVisibility::default(),
@@ -1890,16 +1638,20 @@ fn update_follow_cam(
         return;
     };
 
-    let dt = time.delta_secs();
-    let target = player_t.translation;
+    follow_camera(follow, &mut cam_t, player_t, time.delta_secs());
+}
+
+// Move the camera towards its place behind `target` (a truck), looking ahead of it
+fn follow_camera(follow: &FollowCam, cam_t: &mut Transform, target_t: &Transform, dt: f32) {
+    let target = target_t.translation;
 
     // Camera stays behind player relative to its facing
-    let cam_offset_world = player_t.rotation * follow.offset;
+    let cam_offset_world = target_t.rotation * follow.offset;
     let desired_cam_pos = target + cam_offset_world;
 
     // Look ahead of the player in their facing direction (forward is +Z in Bevy)
     // Calculate forward direction from player rotation
-    let forward = player_t.rotation * Vec3::Z;
+    let forward = target_t.rotation * Vec3::Z;
     let look_ahead_distance = 8.0; // How far ahead to look
     let desired_look_at = target + forward * look_ahead_distance;
 
@@ -1939,9 +1691,132 @@ fn spawn_grid_once(
         &mut materials,
         client.world_size,
     );
+    if let Some(map) = &client.map {
+        spawn_map_obstacles(&mut commands, &mut meshes, &mut materials, map);
+    }
     *spawned = Some(GridSpawned(true));
 }
 
+#[derive(Component)]
+struct MapObstacle;
+
+// Zone boundary wall; `axis` is 0 for walls across X (north/south), 1 for walls across Z
+#[derive(Component)]
+struct ZoneWall {
+    axis: u8,
+    sign: f32,
+}
+
+fn spawn_map_obstacles(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    map: &MapDef,
+) {
+    let obstacle_color = Color::srgb(0.35, 0.38, 0.45);
+    let obstacle_mat = materials.add(StandardMaterial {
+        base_color: obstacle_color,
+        perceptual_roughness: 0.7,
+        metallic: 0.1,
+        ..default()
+    });
+    let ramp_color = Color::srgb(0.55, 0.45, 0.25);
+    let ramp_mat = materials.add(StandardMaterial {
+        base_color: ramp_color,
+        perceptual_roughness: 0.8,
+        ..default()
+    });
+
+    for obstacle in &map.obstacles {
+        let yaw = Quat::from_rotation_y(obstacle.rotation_y);
+        let (mesh, material, transform) = match obstacle.shape {
+            ObstacleShape::Pillar { radius, height } => (
+                meshes.add(Cylinder::new(radius, height)),
+                obstacle_mat.clone(),
+                Transform::from_xyz(obstacle.x, height / 2.0, obstacle.z),
+            ),
+            ObstacleShape::Wall {
+                width,
+                length,
+                height,
+            } => (
+                meshes.add(Cuboid::new(width, height, length)),
+                obstacle_mat.clone(),
+                Transform::from_xyz(obstacle.x, height / 2.0, obstacle.z).with_rotation(yaw),
+            ),
+            ObstacleShape::Ramp {
+                width,
+                length,
+                height,
+            } => {
+                // Thin slab tilted so the front (+Z local) edge sits at `height`
+                let slope_len = (length * length + height * height).sqrt();
+                let pitch = Quat::from_rotation_x(-(height / length).atan());
+                (
+                    meshes.add(Cuboid::new(width, 0.1, slope_len)),
+                    ramp_mat.clone(),
+                    Transform::from_xyz(obstacle.x, height / 2.0, obstacle.z)
+                        .with_rotation(yaw * pitch),
+                )
+            }
+        };
+        commands.spawn((
+            Mesh3d(mesh),
+            MeshMaterial3d(material),
+            transform,
+            GlobalTransform::default(),
+            Visibility::default(),
+            InheritedVisibility::default(),
+            MapObstacle,
+            SceneTag,
+        ));
+    }
+
+    // Shrinking zone boundary, repositioned every frame by update_zone_walls
+    if map.shrink_zone.is_some() {
+        let zone_color = Color::srgba(0.9, 0.2, 0.3, 0.35);
+        let zone_mat = materials.add(StandardMaterial {
+            base_color: zone_color,
+            emissive: Color::srgb(0.6, 0.1, 0.15).into(),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        });
+        let zone_mesh = meshes.add(Cuboid::new(1.0, 4.0, 0.2));
+        for (axis, sign) in [(0u8, 1.0f32), (0, -1.0), (1, 1.0), (1, -1.0)] {
+            commands.spawn((
+                Mesh3d(zone_mesh.clone()),
+                MeshMaterial3d(zone_mat.clone()),
+                Transform::from_xyz(0.0, 2.0, 0.0),
+                GlobalTransform::default(),
+                Visibility::default(),
+                InheritedVisibility::default(),
+                ZoneWall { axis, sign },
+                MapObstacle,
+                SceneTag,
+            ));
+        }
+    }
+}
+
+// Move the zone walls to the server's current zone size
+fn update_zone_walls(cache: Res<WorldCache>, mut q_walls: Query<(&ZoneWall, &mut Transform)>) {
+    let Some(world) = &cache.state else {
+        return;
+    };
+    let half = world.zone_half_size;
+    for (wall, mut transform) in q_walls.iter_mut() {
+        if wall.axis == 0 {
+            transform.translation = Vec3::new(0.0, 2.0, wall.sign * half);
+            transform.rotation = Quat::IDENTITY;
+        } else {
+            transform.translation = Vec3::new(wall.sign * half, 2.0, 0.0);
+            transform.rotation = Quat::from_rotation_y(std::f32::consts::FRAC_PI_2);
+        }
+        transform.scale = Vec3::new(half * 2.0, 1.0, 1.0);
+    }
+}
+
 fn spawn_wire_grid(
     commands: &mut Commands,
     meshes: &mut Assets<Mesh>,
@@ -2138,6 +2013,8 @@ fn update_hud(
     time: Res<Time>,
     mut fps: ResMut<FpsCounter>,
     tracker: Res<PingTracker>,
+    connection: Res<connection::ConnectionStatus>,
+    cache: Res<WorldCache>,
     mut q_window: Query<&mut Window, With<bevy::window::PrimaryWindow>>,
 ) {
     fps.accum_time += time.delta_secs();
@@ -2148,15 +2025,85 @@ fn update_hud(
         fps.accum_frames = 0;
     }
     if let Ok(mut window) = q_window.single_mut() {
-        window.title = format!(
-            "Hover Truck - FPS: {:>3.0}  Ping: {:>3} ms",
-            fps.fps,
-            if tracker.rtt_ms > 0.0 {
-                tracker.rtt_ms.round() as i32
-            } else {
-                -1
+        window.title = if *connection == connection::ConnectionStatus::Connected {
+            let watching = cache.state.as_ref().map_or(0, |w| w.spectators);
+            format!(
+                "Hover Truck - FPS: {:>3.0}  Ping: {:>3} ms{}",
+                fps.fps,
+                if tracker.rtt_ms > 0.0 {
+                    tracker.rtt_ms.round() as i32
+                } else {
+                    -1
+                },
+                if watching > 0 {
+                    format!("  Watching: {watching}")
+                } else {
+                    String::new()
+                }
+            )
+        } else {
+            format!("Hover Truck - {}", connection.message())
+        };
+    }
+}
+
+fn setup_announcement(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(16.0),
+                width: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                display: Display::None,
+                ..default()
+            },
+            GlobalZIndex(5),
+            AnnouncementText,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font_size: 22.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(1.0, 0.85, 0.3)),
+                Node {
+                    padding: UiRect::axes(Val::Px(14.0), Val::Px(6.0)),
+                    ..default()
+                },
+                BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+            ));
+        });
+}
+
+// Show the latest server message until its timer runs out
+fn update_announcement(
+    time: Res<Time>,
+    mut announcement: ResMut<Announcement>,
+    mut q_banner: Query<(&mut Node, &Children), With<AnnouncementText>>,
+    mut q_text: Query<&mut Text>,
+) {
+    let Ok((mut node, children)) = q_banner.single_mut() else {
+        return;
+    };
+    let showing = match announcement.timer.as_mut() {
+        Some(timer) => !timer.tick(time.delta()).is_finished(),
+        None => false,
+    };
+    if !showing {
+        announcement.timer = None;
+        node.display = Display::None;
+        return;
+    }
+    node.display = Display::Flex;
+    for child in children.iter() {
+        if let Ok(mut text) = q_text.get_mut(child) {
+            if text.0 != announcement.text {
+                *text = Text::new(announcement.text.clone());
             }
-        );
+        }
     }
 }
 
@@ -2305,6 +2252,7 @@ fn update_loading_screen(
     mut loading: ResMut<LoadingState>,
     mut q_loading: Query<Entity, With<LoadingScreen>>,
     mut q_text: Query<&mut Text, With<LoadingText>>,
+    connection: Res<connection::ConnectionStatus>,
 ) {
     // Tick the minimum display timer if it exists
     if let Some(timer) = &mut loading.min_display_timer {
@@ -2322,11 +2270,11 @@ fn update_loading_screen(
     // Update loading text based on state
     if let Ok(mut text) = q_text.single_mut() {
         let status = if !loading.welcome_received {
-            "Connecting to server..."
+            connection.message()
         } else if !loading.first_state_received {
-            "Syncing with server..."
+            "Syncing with server...".to_string()
         } else {
-            "Loading..."
+            "Loading...".to_string()
         };
         *text = Text::new(status);
     }
@@ -2335,6 +2283,7 @@ fn update_loading_screen(
 // Update player visuals when boosting
 fn update_player_boost_visuals(
     keys: Res<ButtonInput<KeyCode>>,
+    controls: Res<controls::PlayerControls>,
     client: Res<ClientInfo>,
     local_sim: Option<Res<LocalSim>>,
     mut q_local_player: Query<(&LocalPlayer, &MeshMaterial3d<StandardMaterial>)>,
@@ -2351,8 +2300,7 @@ fn update_player_boost_visuals(
     if let Ok((local_player, material_handle)) = q_local_player.single() {
         if let Some(player_state) = sim.sim.state.players.get(&local_player.id) {
             if let Some(material) = materials.get_mut(&material_handle.0) {
-                let boost_pressed = keys.pressed(KeyCode::KeyW);
-                let boost_active = boost_pressed && player_state.boost_meter > 0.0;
+                let boost_active = controls.boost && player_state.boost_meter > 0.0;
 
                 let base_color = Color::srgb(0.2, 0.8, 0.95); // Blue for main player
                 let color = if boost_active {
@@ -2673,6 +2621,7 @@ fn update_minimap(
 // Interpolate server players smoothly between server updates
 fn interpolate_server_players(
     time: Res<Time>,
+    local_sim: Option<Res<LocalSim>>,
     mut q_server_players: Query<(
         &ServerPlayer,
         &mut Transform,
@@ -2680,7 +2629,7 @@ fn interpolate_server_players(
     )>,
 ) {
     let dt = time.delta_secs();
-    let server_tick_interval = 1.0 / 30.0; // 30 TPS = ~0.033 seconds
+    let server_tick_interval = local_sim.map_or(GameConfig::default().dt(), |s| s.sim.cfg.dt());
 
     for (_, mut transform, mut interp) in q_server_players.iter_mut() {
         interp.time_since_update += dt;
@@ -2777,15 +2726,14 @@ fn update_trailer_lines(
 
         // Calculate hitch point on player (back of player)
         let player_forward = player_transform.rotation * Vec3::Z;
-        let player_back_offset = 0.9;
-        let player_hitch_point = player_transform.translation - player_forward * player_back_offset;
+        let player_hitch_point =
+            player_transform.translation - player_forward * sim.sim.cfg.truck_hitch_offset;
 
         // Line from player to first trailer
         if let Some((1, first_cart_transform)) = sorted_carts.first() {
-            let cart_front_offset = 0.7;
             let cart_forward = first_cart_transform.rotation * Vec3::Z;
             let cart_hitch_point =
-                first_cart_transform.translation + cart_forward * cart_front_offset;
+                first_cart_transform.translation + cart_forward * (sim.sim.cfg.cart_length * 0.5);
 
             let line_key = (*player_id, 0);
             if let Some(line_entity) = existing_lines.remove(&line_key) {
@@ -2817,14 +2765,11 @@ fn update_trailer_lines(
             let (order1, transform1) = sorted_carts[i];
             let (_order2, transform2) = sorted_carts[i + 1];
 
-            let cart_back_offset = 0.7;
-            let cart_front_offset = 0.7;
-
             let forward1 = transform1.rotation * Vec3::Z;
-            let hitch1 = transform1.translation - forward1 * cart_back_offset;
+            let hitch1 = transform1.translation - forward1 * (sim.sim.cfg.cart_length * 0.5);
 
             let forward2 = transform2.rotation * Vec3::Z;
-            let hitch2 = transform2.translation + forward2 * cart_front_offset;
+            let hitch2 = transform2.translation + forward2 * (sim.sim.cfg.cart_length * 0.5);
 
             let line_key = (*player_id, *order1);
             if let Some(line_entity) = existing_lines.remove(&line_key) {
@@ -0,0 +1,119 @@
+// Our profile on the server: a token the server knows us by, made up on first run and kept in
+// profile.json next to the game natively and in localStorage on web, plus the name and colour
+// to save with it. `--name <name>` and `--color <#rrggbb>` (?name= and ?color= on web) change
+// them. Servers that keep profiles count our stats under the token; others ignore it.
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use shared::ClientToServer;
+
+#[cfg(target_arch = "wasm32")]
+const STORAGE_KEY: &str = "hover_truck_profile";
+#[cfg(not(target_arch = "wasm32"))]
+const PROFILE_PATH: &str = "profile.json";
+
+#[derive(Resource, Clone, Serialize, Deserialize)]
+pub struct LocalProfile {
+    pub token: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+impl LocalProfile {
+    pub fn load() -> Self {
+        let saved = match load_profile() {
+            Ok(saved) => saved,
+            Err(e) => {
+                warn!("Ignoring saved profile: {e}");
+                None
+            }
+        };
+        let mut changed = saved.is_none();
+        let mut profile = saved.unwrap_or_else(|| LocalProfile {
+            token: format!("{:032x}", rand::random::<u128>()),
+            name: String::new(),
+            color: None,
+        });
+        if let Some(name) = launch_option("name").filter(|name| *name != profile.name) {
+            profile.name = name;
+            changed = true;
+        }
+        if let Some(color) = launch_option("color").filter(|c| profile.color.as_ref() != Some(c)) {
+            profile.color = Some(color);
+            changed = true;
+        }
+        if changed {
+            if let Err(e) = save_profile(&profile) {
+                warn!("Profile not saved: {e}");
+            }
+        }
+        profile
+    }
+
+    // What to tell the server about us after joining, if we picked a name or colour
+    pub fn hello(&self) -> Option<ClientToServer> {
+        (!self.name.is_empty() || self.color.is_some()).then(|| ClientToServer::Hello {
+            name: self.name.clone(),
+            color: self.color.clone(),
+        })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn launch_option(name: &str) -> Option<String> {
+    let flag = format!("--{name}");
+    let mut args = std::env::args().skip_while(|a| *a != flag);
+    args.next()?;
+    args.next().filter(|a| !a.starts_with("--"))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn launch_option(name: &str) -> Option<String> {
+    let search = web_sys::window()?.location().search().ok()?;
+    web_sys::UrlSearchParams::new_with_str(&search)
+        .ok()?
+        .get(name)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn load_profile() -> Result<Option<LocalProfile>, String> {
+    if !std::path::Path::new(PROFILE_PATH).exists() {
+        return Ok(None);
+    }
+    let json = std::fs::read_to_string(PROFILE_PATH).map_err(|e| e.to_string())?;
+    serde_json::from_str(&json)
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_profile(profile: &LocalProfile) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(profile).map_err(|e| e.to_string())?;
+    std::fs::write(PROFILE_PATH, json).map_err(|e| e.to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Result<web_sys::Storage, String> {
+    web_sys::window()
+        .and_then(|w| w.local_storage().ok().flatten())
+        .ok_or_else(|| "localStorage unavailable".to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn load_profile() -> Result<Option<LocalProfile>, String> {
+    let Some(json) = local_storage()?.get_item(STORAGE_KEY).ok().flatten() else {
+        return Ok(None);
+    };
+    serde_json::from_str(&json)
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn save_profile(profile: &LocalProfile) -> Result<(), String> {
+    let json = serde_json::to_string(profile).map_err(|e| e.to_string())?;
+    local_storage()?
+        .set_item(STORAGE_KEY, &json)
+        .map_err(|e| format!("{e:?}"))
+}
@@ -0,0 +1,238 @@
+// Spectator mode: launched with --spectate (SPECTATE=1, or ?spectate on web) the client joins
+// without a truck and watches the match. The camera follows one player at a time, Tab and
+// Shift+Tab cycling through them, or flies freely: F switches, then WASD moves, Q/E turns and
+// Z/X changes height.
+use bevy::prelude::*;
+use shared::{PlayerId, WorldState};
+
+use crate::chat::ChatState;
+use crate::{follow_camera, ClientInfo, FollowCam, ServerPlayer, WorldCache};
+
+const FREE_CAM_SPEED: f32 = 30.0; // Units per second
+const FREE_CAM_TURN: f32 = 1.5; // Radians per second
+const FREE_CAM_CLIMB: f32 = 20.0;
+const FREE_CAM_HEIGHT: (f32, f32) = (3.0, 150.0);
+const FREE_CAM_PITCH: f32 = -0.6; // Looking down at the arena
+
+// Returns true when the client was launched to spectate
+pub fn spectate_requested() -> bool {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::env::args().any(|a| a == "--spectate")
+            || std::env::var("SPECTATE").is_ok_and(|v| !v.is_empty() && v != "0")
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        let Some(search) = web_sys::window().and_then(|w| w.location().search().ok()) else {
+            return false;
+        };
+        web_sys::UrlSearchParams::new_with_str(&search).is_ok_and(|params| params.has("spectate"))
+    }
+}
+
+#[derive(Resource, Default)]
+struct SpectatorCam {
+    following: Option<PlayerId>,
+    free: bool,
+}
+
+#[derive(Component)]
+struct SpectatorHud;
+
+pub struct SpectatePlugin;
+
+impl Plugin for SpectatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpectatorCam>()
+            .add_systems(Startup, setup_spectator_hud)
+            .add_systems(
+                Update,
+                (
+                    (spectator_keys, update_spectator_cam)
+                        .chain()
+                        .run_if(|client: Res<ClientInfo>| client.spectator),
+                    update_spectator_hud,
+                )
+                    .chain(),
+            );
+    }
+}
+
+// Players in a stable order to cycle through, and the one after (or before) `current`
+fn next_player(world: &WorldState, current: Option<PlayerId>, back: bool) -> Option<PlayerId> {
+    let mut ids: Vec<PlayerId> = world.players.keys().copied().collect();
+    if ids.is_empty() {
+        return None;
+    }
+    ids.sort();
+    let next = match current.and_then(|id| ids.iter().position(|p| *p == id)) {
+        Some(i) if back => (i + ids.len() - 1) % ids.len(),
+        Some(i) => (i + 1) % ids.len(),
+        None => 0,
+    };
+    Some(ids[next])
+}
+
+// The longest truck, to have something worth watching when we start or our player leaves
+fn leader(world: &WorldState) -> Option<PlayerId> {
+    world
+        .players
+        .values()
+        .filter(|p| p.alive)
+        .max_by_key(|p| (p.trailer.len(), p.id))
+        .map(|p| p.id)
+}
+
+fn spectator_keys(
+    keys: Res<ButtonInput<KeyCode>>,
+    chat: Res<ChatState>,
+    cache: Res<WorldCache>,
+    mut cam: ResMut<SpectatorCam>,
+) {
+    let Some(world) = &cache.state else {
+        return;
+    };
+    if cam
+        .following
+        .is_none_or(|id| !world.players.contains_key(&id))
+    {
+        cam.following = leader(world).or_else(|| next_player(world, None, false));
+    }
+    if chat.typing {
+        return;
+    }
+    if keys.just_pressed(KeyCode::Tab) {
+        let back = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+        cam.following = next_player(world, cam.following, back);
+        cam.free = false;
+    }
+    if keys.just_pressed(KeyCode::KeyF) {
+        cam.free = !cam.free;
+    }
+}
+
+fn update_spectator_cam(
+    time: Res<Time>,
+    keys: Res<ButtonInput<KeyCode>>,
+    chat: Res<ChatState>,
+    client: Res<ClientInfo>,
+    cam: Res<SpectatorCam>,
+    q_players: Query<(&ServerPlayer, &Transform), Without<Camera>>,
+    mut q_cam: Query<(&FollowCam, &mut Transform), With<Camera>>,
+) {
+    let Ok((follow, mut cam_t)) = q_cam.single_mut() else {
+        return;
+    };
+    let dt = time.delta_secs();
+    if !cam.free {
+        let target = cam
+            .following
+            .and_then(|id| q_players.iter().find(|(p, _)| p.id == id));
+        if let Some((_, target_t)) = target {
+            follow_camera(follow, &mut cam_t, target_t, dt);
+        }
+        return;
+    }
+
+    // Free camera: turn and move over the ground, keeping a fixed downward pitch
+    let (mut yaw, _, _) = cam_t.rotation.to_euler(EulerRot::YXZ);
+    let mut movement = Vec3::ZERO;
+    let mut climb = 0.0;
+    if !chat.typing {
+        let axis = |neg: KeyCode, pos: KeyCode| {
+            keys.pressed(pos) as i32 as f32 - keys.pressed(neg) as i32 as f32
+        };
+        yaw += axis(KeyCode::KeyE, KeyCode::KeyQ) * FREE_CAM_TURN * dt;
+        movement = Vec3::new(
+            axis(KeyCode::KeyA, KeyCode::KeyD),
+            0.0,
+            -axis(KeyCode::KeyS, KeyCode::KeyW),
+        );
+        climb = axis(KeyCode::KeyZ, KeyCode::KeyX);
+    }
+    let rotation = Quat::from_rotation_y(yaw);
+    cam_t.rotation = rotation * Quat::from_rotation_x(FREE_CAM_PITCH);
+    cam_t.translation += rotation * movement.normalize_or_zero() * FREE_CAM_SPEED * dt;
+    cam_t.translation.y = (cam_t.translation.y + climb * FREE_CAM_CLIMB * dt)
+        .clamp(FREE_CAM_HEIGHT.0, FREE_CAM_HEIGHT.1);
+    // Don't wander off too far from the arena
+    let bound = client.world_size * 1.5;
+    cam_t.translation.x = cam_t.translation.x.clamp(-bound, bound);
+    cam_t.translation.z = cam_t.translation.z.clamp(-bound, bound);
+}
+
+fn setup_spectator_hud(mut commands: Commands) {
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 16.0,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(12.0),
+            top: Val::Px(12.0),
+            padding: UiRect::axes(Val::Px(8.0), Val::Px(4.0)),
+            display: Display::None,
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
+        GlobalZIndex(4),
+        SpectatorHud,
+    ));
+}
+
+// Who we are watching, how many others are watching, and the keys
+fn update_spectator_hud(
+    client: Res<ClientInfo>,
+    cache: Res<WorldCache>,
+    cam: Res<SpectatorCam>,
+    mut q_hud: Query<(&mut Text, &mut Node), With<SpectatorHud>>,
+) {
+    let Ok((mut text, mut node)) = q_hud.single_mut() else {
+        return;
+    };
+    let display = if client.spectator {
+        Display::Flex
+    } else {
+        Display::None
+    };
+    if node.display != display {
+        node.display = display;
+    }
+    if !client.spectator {
+        return;
+    }
+    let Some(world) = &cache.state else {
+        return;
+    };
+    let watching = match world.spectators {
+        1 => "1 spectator".to_string(),
+        n => format!("{n} spectators"),
+    };
+    let hud = if cam.free {
+        format!("Free camera - {watching}\nWASD: move  Q/E: turn  Z/X: height  F: follow players")
+    } else {
+        let target = match cam.following.and_then(|id| world.players.get(&id)) {
+            Some(player) => {
+                let name = format!("Truck {}", &player.id.simple().to_string()[..4]);
+                let bot = if world.bots.contains(&player.id) {
+                    " (bot)"
+                } else {
+                    ""
+                };
+                let state = if player.alive { "" } else { ", respawning" };
+                format!(
+                    "Watching {name}{bot}, trailer {}{state}",
+                    player.trailer.len()
+                )
+            }
+            None => "Waiting for players".to_string(),
+        };
+        format!("{target} - {watching}\nTab/Shift+Tab: next/previous player  F: free camera")
+    };
+    if text.0 != hud {
+        text.0 = hud;
+    }
+}
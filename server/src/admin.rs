@@ -0,0 +1,445 @@
+use axum::{
+	async_trait,
+	extract::{FromRequestParts, Path, State},
+	http::{request::Parts, StatusCode},
+	response::IntoResponse,
+	routing::{delete, get, post},
+	Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use shared::{BotDifficulty, BotPersonality, GameConfig, PlayerId, ServerToClient};
+use std::net::IpAddr;
+use tracing::info;
+
+use crate::{reload, remove_player, AppState};
+
+// Admin routes, all requiring `Authorization: Bearer $ADMIN_TOKEN`.
+// Without ADMIN_TOKEN set every admin request is rejected.
+pub fn router() -> Router<AppState> {
+	Router::new()
+		.route("/admin/bots", get(get_bots).post(update_bot_settings))
+		.route("/admin/bots/add", post(add_bots))
+		.route("/admin/bots/:id", post(update_bot).delete(remove_bot))
+		.route("/admin/players", get(get_players))
+		.route("/admin/players/:id/kick", post(kick_player))
+		.route("/admin/spectators", get(get_spectators))
+		.route("/admin/bans", get(get_bans).post(ban))
+		.route("/admin/bans/:ip", delete(unban))
+		.route("/admin/config", get(get_config).post(update_config))
+		.route("/admin/config/reload", post(reload_config))
+		.route("/admin/match/restart", post(restart_match))
+		.route("/admin/broadcast", post(broadcast))
+}
+
+type AdminResult<T> = Result<T, (StatusCode, String)>;
+
+// Proof that the request carries the admin token. Taken ahead of any body extractor, so an
+// unauthenticated request is turned away before its body is parsed.
+struct Admin;
+
+#[async_trait]
+impl FromRequestParts<AppState> for Admin {
+	type Rejection = (StatusCode, String);
+
+	async fn from_request_parts(parts: &mut Parts, state: &AppState) -> AdminResult<Self> {
+		let Some(token) = state.admin_token.as_deref() else {
+			return Err((StatusCode::FORBIDDEN, "admin API disabled (ADMIN_TOKEN not set)".to_string()));
+		};
+		let given = parts
+			.headers
+			.get(axum::http::header::AUTHORIZATION)
+			.and_then(|v| v.to_str().ok())
+			.and_then(|v| v.strip_prefix("Bearer "));
+		match given {
+			Some(given) if constant_time_eq(given.as_bytes(), token.as_bytes()) => Ok(Admin),
+			_ => Err((StatusCode::UNAUTHORIZED, "missing or invalid admin token".to_string())),
+		}
+	}
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+	a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// A difficulty given either as a preset name ("easy", "normal", "hard") or explicit values
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DifficultyArg {
+	Preset(String),
+	Custom(BotDifficulty),
+}
+
+impl DifficultyArg {
+	fn resolve(self) -> AdminResult<BotDifficulty> {
+		match self {
+			DifficultyArg::Preset(name) => name.parse().map_err(|e| (StatusCode::BAD_REQUEST, e)),
+			DifficultyArg::Custom(difficulty) => Ok(difficulty.clamped()),
+		}
+	}
+}
+
+#[derive(Serialize)]
+struct BotInfo {
+	id: PlayerId,
+	personality: &'static str,
+	difficulty: BotDifficulty,
+	alive: bool,
+}
+
+#[derive(Serialize)]
+struct BotsResponse {
+	target_trucks: usize,
+	default_difficulty: BotDifficulty,
+	humans: usize,
+	bots: Vec<BotInfo>,
+}
+
+async fn get_bots(State(state): State<AppState>, _: Admin) -> AdminResult<impl IntoResponse> {
+	let sim = state.sim.lock().await;
+	let bots = sim.bot_brains.iter()
+		.map(|(id, brain)| BotInfo {
+			id: *id,
+			personality: brain.controller.name(),
+			difficulty: brain.difficulty,
+			alive: sim.state.players.get(id).is_some_and(|p| p.alive),
+		})
+		.collect();
+	Ok(Json(BotsResponse {
+		target_trucks: sim.target_trucks,
+		default_difficulty: sim.bot_difficulty,
+		humans: sim.human_count(),
+		bots,
+	}))
+}
+
+#[derive(Deserialize)]
+struct BotSettingsRequest {
+	target_trucks: Option<usize>,
+	difficulty: Option<DifficultyArg>,
+	#[serde(default)]
+	apply_to_existing: bool, // Also change the difficulty of bots already in the game
+}
+
+async fn update_bot_settings(
+	State(state): State<AppState>,
+	_: Admin,
+	Json(req): Json<BotSettingsRequest>,
+) -> AdminResult<impl IntoResponse> {
+	let difficulty = req.difficulty.map(DifficultyArg::resolve).transpose()?;
+	let mut sim = state.sim.lock().await;
+	if let Some(target) = req.target_trucks {
+		sim.target_trucks = target;
+		info!("admin: bot fill target set to {target} trucks");
+	}
+	if let Some(difficulty) = difficulty {
+		sim.bot_difficulty = difficulty;
+		if req.apply_to_existing {
+			let ids: Vec<PlayerId> = sim.bots.iter().copied().collect();
+			for id in ids {
+				sim.set_bot_difficulty(&id, difficulty);
+			}
+		}
+		info!("admin: bot difficulty set to {difficulty:?}");
+	}
+	sim.balance_bots();
+	Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+struct BotUpdateRequest {
+	personality: Option<String>,
+	difficulty: Option<DifficultyArg>,
+}
+
+async fn update_bot(
+	State(state): State<AppState>,
+	_: Admin,
+	Path(id): Path<PlayerId>,
+	Json(req): Json<BotUpdateRequest>,
+) -> AdminResult<impl IntoResponse> {
+	let personality = req.personality
+		.map(|p| p.parse::<BotPersonality>())
+		.transpose()
+		.map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+	let difficulty = req.difficulty.map(DifficultyArg::resolve).transpose()?;
+	let mut sim = state.sim.lock().await;
+	if !sim.bots.contains(&id) {
+		return Err((StatusCode::NOT_FOUND, format!("no bot with id {id}")));
+	}
+	if let Some(personality) = personality {
+		sim.set_bot_controller(&id, personality.controller());
+	}
+	if let Some(difficulty) = difficulty {
+		sim.set_bot_difficulty(&id, difficulty);
+	}
+	Ok(StatusCode::NO_CONTENT)
+}
+
+// Gameplay tuning the sim is running with
+async fn get_config(State(state): State<AppState>, _: Admin) -> AdminResult<impl IntoResponse> {
+	let cfg = state.sim.lock().await.cfg.clone();
+	Ok(Json(cfg))
+}
+
+// Load the configuration again and apply changed gameplay settings
+async fn reload_config(State(state): State<AppState>, _: Admin) -> AdminResult<impl IntoResponse> {
+	match reload::reload(&state).await {
+		Ok(outcome) => Ok(Json(outcome)),
+		Err(e) => Err((StatusCode::BAD_REQUEST, format!("{e:#}"))),
+	}
+}
+
+#[derive(Deserialize)]
+struct AddBotsRequest {
+	#[serde(default = "one")]
+	count: usize,
+	personality: Option<String>, // Random if unset
+	difficulty: Option<DifficultyArg>, // The room's default if unset
+}
+
+fn one() -> usize {
+	1
+}
+
+// Add bots on top of the fill target (which is raised to keep them, if auto fill is on)
+async fn add_bots(
+	State(state): State<AppState>,
+	_: Admin,
+	Json(req): Json<AddBotsRequest>,
+) -> AdminResult<impl IntoResponse> {
+	let personality = req.personality
+		.map(|p| p.parse::<BotPersonality>())
+		.transpose()
+		.map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+	let difficulty = req.difficulty.map(DifficultyArg::resolve).transpose()?;
+	if req.count > 64 {
+		return Err((StatusCode::BAD_REQUEST, "at most 64 bots at a time".to_string()));
+	}
+	let mut sim = state.sim.lock().await;
+	let mut added = Vec::new();
+	for _ in 0..req.count {
+		let id = match personality {
+			Some(personality) => {
+				let difficulty = difficulty.unwrap_or(sim.bot_difficulty);
+				sim.add_bot_with(personality.controller(), difficulty)
+			}
+			None => {
+				let id = sim.add_bot();
+				if let Some(difficulty) = difficulty {
+					sim.set_bot_difficulty(&id, difficulty);
+				}
+				id
+			}
+		};
+		added.push(id);
+	}
+	if sim.target_trucks > 0 {
+		sim.target_trucks += added.len();
+	}
+	info!("admin: added {} bots", added.len());
+	Ok(Json(added))
+}
+
+// Remove a bot (and lower the fill target so it isn't replaced, if auto fill is on)
+async fn remove_bot(
+	State(state): State<AppState>,
+	_: Admin,
+	Path(id): Path<PlayerId>,
+) -> AdminResult<impl IntoResponse> {
+	let mut sim = state.sim.lock().await;
+	if !sim.bots.contains(&id) {
+		return Err((StatusCode::NOT_FOUND, format!("no bot with id {id}")));
+	}
+	sim.remove_player(&id);
+	if sim.target_trucks > 0 {
+		sim.target_trucks -= 1;
+	}
+	info!("admin: removed bot {id}");
+	Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Serialize)]
+struct PlayerInfo {
+	id: PlayerId,
+	bot: bool,
+	alive: bool,
+	trailer: usize,
+	ip: Option<IpAddr>, // Humans that are connected
+	autopilot: bool, // Human whose connection dropped; the truck waits for them to resume
+}
+
+async fn get_players(State(state): State<AppState>, _: Admin) -> AdminResult<impl IntoResponse> {
+	let connected = state.clients.list();
+	let sim = state.sim.lock().await;
+	let mut players: Vec<PlayerInfo> = sim.state.players.values()
+		.map(|p| PlayerInfo {
+			id: p.id,
+			bot: sim.bots.contains(&p.id),
+			alive: p.alive,
+			trailer: p.trailer.len(),
+			ip: connected.get(&p.id).copied(),
+			autopilot: sim.autopilot.contains_key(&p.id),
+		})
+		.collect();
+	players.sort_by_key(|p| (p.bot, std::cmp::Reverse(p.trailer)));
+	Ok(Json(players))
+}
+
+#[derive(Serialize)]
+struct SpectatorInfo {
+	id: PlayerId,
+	ip: IpAddr,
+}
+
+async fn get_spectators(State(state): State<AppState>, _: Admin) -> AdminResult<impl IntoResponse> {
+	let mut spectators: Vec<SpectatorInfo> =
+		state.clients.spectators().into_iter().map(|(id, ip)| SpectatorInfo { id, ip }).collect();
+	spectators.sort_by_key(|s| (s.ip, s.id));
+	Ok(Json(spectators))
+}
+
+#[derive(Deserialize, Default)]
+struct KickRequest {
+	reason: Option<String>, // Shown to the player
+}
+
+// Disconnect a human player and remove their truck, or disconnect a spectator. They can join
+// again unless banned.
+async fn kick_player(
+	State(state): State<AppState>,
+	_: Admin,
+	Path(id): Path<PlayerId>,
+	req: Option<Json<KickRequest>>,
+) -> AdminResult<impl IntoResponse> {
+	let reason = req.and_then(|Json(r)| r.reason).unwrap_or_else(|| "kicked by an admin".to_string());
+	{
+		let sim = state.sim.lock().await;
+		if sim.bots.contains(&id) {
+			return Err((StatusCode::BAD_REQUEST, format!("{id} is a bot; remove it with DELETE /admin/bots/{id}")));
+		}
+		if !sim.state.players.contains_key(&id) {
+			if state.clients.spectators().contains_key(&id) {
+				state.clients.kick(&id, &reason);
+				info!("admin: kicked spectator {id} ({reason})");
+				return Ok(StatusCode::NO_CONTENT);
+			}
+			return Err((StatusCode::NOT_FOUND, format!("no player with id {id}")));
+		}
+	}
+	// A connected player's connection removes them as it closes; one waiting to resume goes now
+	if !state.clients.kick(&id, &reason) {
+		state.sessions.end_player(&id);
+		remove_player(&state, &id).await;
+	}
+	info!("admin: kicked player {id} ({reason})");
+	Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+struct BanRequest {
+	ip: Option<IpAddr>,
+	player_id: Option<PlayerId>, // Ban the address this player is connected from
+}
+
+#[derive(Serialize)]
+struct BanResponse {
+	ip: IpAddr,
+	kicked: Vec<PlayerId>,
+}
+
+// Ban an address until the server restarts, kicking everyone connected from it
+async fn ban(
+	State(state): State<AppState>,
+	_: Admin,
+	Json(req): Json<BanRequest>,
+) -> AdminResult<impl IntoResponse> {
+	let ip = match (req.ip, req.player_id) {
+		(Some(ip), None) => ip,
+		(None, Some(id)) => state.clients.ip(&id)
+			.ok_or_else(|| (StatusCode::NOT_FOUND, format!("player {id} is not connected")))?,
+		_ => return Err((StatusCode::BAD_REQUEST, "give either ip or player_id".to_string())),
+	};
+	state.bans.add(ip);
+	let kicked = state.clients.kick_ip(ip, "banned from this server");
+	info!("admin: banned {ip} ({} players kicked)", kicked.len());
+	Ok(Json(BanResponse { ip, kicked }))
+}
+
+async fn get_bans(State(state): State<AppState>, _: Admin) -> AdminResult<impl IntoResponse> {
+	Ok(Json(state.bans.list()))
+}
+
+async fn unban(
+	State(state): State<AppState>,
+	_: Admin,
+	Path(ip): Path<IpAddr>,
+) -> AdminResult<impl IntoResponse> {
+	if !state.bans.remove(&ip) {
+		return Err((StatusCode::NOT_FOUND, format!("{ip} is not banned")));
+	}
+	info!("admin: unbanned {ip}");
+	Ok(StatusCode::NO_CONTENT)
+}
+
+// Change some gameplay settings, given as a JSON object like {"player_speed": 14}. Applied
+// between ticks and pushed to clients like a config reload; a later reload of the config file
+// replaces them. The sim stays locked from reading the current settings to applying the merged
+// ones, so a reload can't land in between and be lost.
+async fn update_config(
+	State(state): State<AppState>,
+	_: Admin,
+	Json(changes): Json<serde_json::Map<String, serde_json::Value>>,
+) -> AdminResult<impl IntoResponse> {
+	let mut sim = state.sim.lock().await;
+	let serde_json::Value::Object(mut merged) = serde_json::to_value(&sim.cfg).unwrap() else {
+		unreachable!("GameConfig serializes to an object");
+	};
+	for (key, value) in changes {
+		if !merged.contains_key(&key) {
+			return Err((StatusCode::BAD_REQUEST, format!("unknown setting {key:?}")));
+		}
+		merged.insert(key, value);
+	}
+	let cfg: GameConfig = serde_json::from_value(merged.into())
+		.map_err(|e| (StatusCode::BAD_REQUEST, format!("invalid settings: {e}")))?;
+	cfg.validate().map_err(|errors| (StatusCode::BAD_REQUEST, errors.join("\n")))?;
+	let applied = reload::swap_gameplay(&state, &mut sim, cfg);
+	drop(sim);
+	for change in &applied {
+		info!("admin: {change}");
+	}
+	Ok(Json(applied))
+}
+
+// Respawn everyone at their starting length, clear the items and reset the shrinking zone
+async fn restart_match(State(state): State<AppState>, _: Admin) -> AdminResult<impl IntoResponse> {
+	state.sim.lock().await.restart_match();
+	state.clients.send_all(&ServerToClient::ServerMessage("A new match has started".to_string()));
+	info!("admin: match restarted");
+	Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+struct BroadcastRequest {
+	message: String,
+}
+
+#[derive(Serialize)]
+struct BroadcastResponse {
+	sent: usize, // Connected clients it was queued for
+}
+
+// Show a message to every connected player
+async fn broadcast(
+	State(state): State<AppState>,
+	_: Admin,
+	Json(req): Json<BroadcastRequest>,
+) -> AdminResult<impl IntoResponse> {
+	let message = req.message.trim();
+	if message.is_empty() || message.chars().count() > 280 {
+		return Err((StatusCode::BAD_REQUEST, "message must be 1 to 280 characters".to_string()));
+	}
+	let sent = state.clients.send_all(&ServerToClient::ServerMessage(message.to_string()));
+	info!("admin: broadcast to {sent} clients: {message}");
+	Ok(Json(BroadcastResponse { sent }))
+}
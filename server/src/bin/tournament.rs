@@ -0,0 +1,245 @@
+// Headless bot-vs-bot tournament: runs seeded `GameSim` matches with only bots and reports
+// per-entrant stats as JSON or CSV.
+//
+//   cargo run --release -p server --bin tournament -- \
+//       --bots collector:hard,hunter:hard,coward:normal --matches 20 --duration 120 --format csv
+
+use std::{collections::BTreeMap, path::PathBuf, str::FromStr};
+
+use anyhow::{bail, Context};
+use clap::{Parser, ValueEnum};
+use rayon::prelude::*;
+use serde::Serialize;
+use shared::{BotDifficulty, BotPersonality, DeathCause, GameConfig, GameEvent, GameSim, MapDef, PlayerId};
+
+#[derive(Parser)]
+#[command(about = "Run bot-only matches and report per-bot stats")]
+struct Args {
+	/// Comma-separated entrants as personality[:difficulty], e.g. collector:hard,hunter,coward:easy
+	#[arg(long, default_value = "collector,hunter,coward")]
+	bots: String,
+	/// Number of matches to run
+	#[arg(long, default_value_t = 10)]
+	matches: u32,
+	/// Length of each match in seconds of game time
+	#[arg(long, default_value_t = 120.0)]
+	duration: f32,
+	/// Seed of the first match; match i uses seed + i
+	#[arg(long, default_value_t = 1)]
+	seed: u64,
+	/// Arena to play on (JSON or RON); empty square world if omitted
+	#[arg(long)]
+	map: Option<PathBuf>,
+	#[arg(long, value_enum, default_value_t = Format::Json)]
+	format: Format,
+	/// Write the report here instead of stdout
+	#[arg(long)]
+	out: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+	Json,
+	Csv,
+}
+
+#[derive(Clone, Copy)]
+struct Entrant {
+	personality: BotPersonality,
+	difficulty: BotDifficulty,
+	label_difficulty: &'static str,
+}
+
+impl Entrant {
+	fn label(&self) -> String {
+		format!("{:?}:{}", self.personality, self.label_difficulty).to_lowercase()
+	}
+}
+
+impl FromStr for Entrant {
+	type Err = anyhow::Error;
+
+	fn from_str(s: &str) -> anyhow::Result<Self> {
+		let (personality, difficulty) = s.split_once(':').unwrap_or((s, "normal"));
+		let label_difficulty = match difficulty.to_ascii_lowercase().as_str() {
+			"easy" => "easy",
+			"normal" => "normal",
+			"hard" => "hard",
+			other => bail!("unknown difficulty '{other}' in '{s}' (expected easy, normal or hard)"),
+		};
+		Ok(Self {
+			personality: personality.parse().map_err(anyhow::Error::msg)?,
+			difficulty: difficulty.parse().map_err(anyhow::Error::msg)?,
+			label_difficulty,
+		})
+	}
+}
+
+// Raw totals for one entrant, summed over matches
+#[derive(Default, Clone)]
+struct Tally {
+	matches: u32,
+	lives: u32,
+	alive_ticks: u64,
+	length_sum: u64, // Trailer length summed over alive ticks
+	max_length: usize,
+	pickups: u32,
+	kills: u32,
+	deaths: BTreeMap<String, u32>,
+}
+
+impl Tally {
+	fn merge(&mut self, other: &Tally) {
+		self.matches += other.matches;
+		self.lives += other.lives;
+		self.alive_ticks += other.alive_ticks;
+		self.length_sum += other.length_sum;
+		self.max_length = self.max_length.max(other.max_length);
+		self.pickups += other.pickups;
+		self.kills += other.kills;
+		for (cause, n) in &other.deaths {
+			*self.deaths.entry(cause.clone()).or_default() += n;
+		}
+	}
+}
+
+#[derive(Serialize)]
+struct EntrantReport {
+	bot: String,
+	matches: u32,
+	avg_length: f32,
+	max_length: usize,
+	pickups: u32,
+	kills: u32,
+	deaths: u32,
+	deaths_by_cause: BTreeMap<String, u32>,
+	time_alive_secs: f32,
+	avg_life_secs: f32,
+}
+
+const CAUSES: [DeathCause; 5] = [DeathCause::Wall, DeathCause::Obstacle, DeathCause::Zone, DeathCause::Truck, DeathCause::Trailer];
+
+fn cause_name(cause: DeathCause) -> String {
+	format!("{cause:?}").to_lowercase()
+}
+
+fn run_match(entrants: &[Entrant], map: &Option<MapDef>, ticks: u64, seed: u64) -> Vec<Tally> {
+	let cfg = GameConfig::default();
+	let mut sim = match map {
+		Some(map) => GameSim::with_map(cfg, map.clone()),
+		None => GameSim::new(cfg),
+	};
+	sim.reseed(seed);
+	let ids: Vec<PlayerId> = entrants.iter()
+		.map(|e| sim.add_bot_with(e.personality.controller(), e.difficulty))
+		.collect();
+	let mut tallies = vec![Tally { matches: 1, lives: 1, ..Default::default() }; entrants.len()];
+
+	for _ in 0..ticks {
+		sim.step();
+		for event in &sim.events {
+			match *event {
+				GameEvent::ItemPickup { player } => {
+					if let Some(i) = ids.iter().position(|id| *id == player) {
+						tallies[i].pickups += 1;
+					}
+				}
+				GameEvent::Death { player, cause, killer, .. } => {
+					if let Some(i) = ids.iter().position(|id| *id == player) {
+						tallies[i].lives += 1;
+						*tallies[i].deaths.entry(cause_name(cause)).or_default() += 1;
+					}
+					if let Some(i) = killer.and_then(|k| ids.iter().position(|id| *id == k)) {
+						tallies[i].kills += 1;
+					}
+				}
+			}
+		}
+		for (i, id) in ids.iter().enumerate() {
+			if let Some(player) = sim.state.players.get(id).filter(|p| p.alive) {
+				let tally = &mut tallies[i];
+				tally.alive_ticks += 1;
+				tally.length_sum += player.trailer.len() as u64;
+				tally.max_length = tally.max_length.max(player.trailer.len());
+			}
+		}
+	}
+	tallies
+}
+
+fn main() -> anyhow::Result<()> {
+	let args = Args::parse();
+	let entrants: Vec<Entrant> = args.bots.split(',')
+		.map(str::trim)
+		.filter(|s| !s.is_empty())
+		.map(str::parse)
+		.collect::<anyhow::Result<_>>()?;
+	if entrants.is_empty() {
+		bail!("--bots must name at least one bot");
+	}
+	if args.duration.is_nan() || args.duration <= 0.0 {
+		bail!("--duration must be positive");
+	}
+	let map = args.map.as_ref()
+		.map(|path| MapDef::load(path).with_context(|| format!("failed to load map {}", path.display())))
+		.transpose()?;
+
+	let dt = GameConfig::default().dt();
+	let ticks = (args.duration / dt).round() as u64;
+	let results: Vec<Vec<Tally>> = (0..args.matches)
+		.into_par_iter()
+		.map(|i| run_match(&entrants, &map, ticks, args.seed + i as u64))
+		.collect();
+
+	let mut totals = vec![Tally::default(); entrants.len()];
+	for result in &results {
+		for (total, tally) in totals.iter_mut().zip(result) {
+			total.merge(tally);
+		}
+	}
+
+	let reports: Vec<EntrantReport> = entrants.iter()
+		.zip(&totals)
+		.map(|(entrant, t)| {
+			let time_alive_secs = t.alive_ticks as f32 * dt;
+			EntrantReport {
+				bot: entrant.label(),
+				matches: t.matches,
+				avg_length: if t.alive_ticks > 0 { t.length_sum as f32 / t.alive_ticks as f32 } else { 0.0 },
+				max_length: t.max_length,
+				pickups: t.pickups,
+				kills: t.kills,
+				deaths: t.deaths.values().sum(),
+				deaths_by_cause: t.deaths.clone(),
+				time_alive_secs,
+				avg_life_secs: if t.lives > 0 { time_alive_secs / t.lives as f32 } else { 0.0 },
+			}
+		})
+		.collect();
+
+	let output = match args.format {
+		Format::Json => serde_json::to_string_pretty(&reports)? + "\n",
+		Format::Csv => to_csv(&reports),
+	};
+	match &args.out {
+		Some(path) => std::fs::write(path, output).with_context(|| format!("failed to write {}", path.display()))?,
+		None => print!("{output}"),
+	}
+	Ok(())
+}
+
+fn to_csv(reports: &[EntrantReport]) -> String {
+	let mut out = String::from("bot,matches,avg_length,max_length,pickups,kills,deaths");
+	for cause in CAUSES {
+		out.push_str(&format!(",deaths_{}", cause_name(cause)));
+	}
+	out.push_str(",time_alive_secs,avg_life_secs\n");
+	for r in reports {
+		out.push_str(&format!("{},{},{:.2},{},{},{},{}", r.bot, r.matches, r.avg_length, r.max_length, r.pickups, r.kills, r.deaths));
+		for cause in CAUSES {
+			out.push_str(&format!(",{}", r.deaths_by_cause.get(&cause_name(cause)).copied().unwrap_or(0)));
+		}
+		out.push_str(&format!(",{:.1},{:.1}\n", r.time_alive_secs, r.avg_life_secs));
+	}
+	out
+}
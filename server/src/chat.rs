@@ -0,0 +1,100 @@
+// Chat and quick-chat emotes. Messages are cleaned up (control characters, whitespace, length)
+// and blocked words masked before they are sent to the room; each connection is throttled, and
+// repeating the same message is refused. Rejected messages get a ChatRejected back so the
+// sender knows why; they don't count as protocol errors.
+
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use shared::MAX_CHAT_CHARS;
+
+use crate::limits::RateLimiter;
+
+// Words masked out of chat unless chat.filter_profanity is off. Matched case-insensitively
+// at the start of a word, so plurals and "-ing" forms are caught too.
+const BLOCKED_WORDS: &[&str] = &["fuck", "shit", "cunt", "bitch", "asshole", "wank"];
+
+// The same message again within this long is refused as spam
+const REPEAT_WINDOW: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChatConfig {
+	pub enabled: bool,
+	pub messages_per_sec: f64, // Sustained chat rate per player; emotes count too
+	pub burst: f64,
+	pub filter_profanity: bool, // Mask the built-in list of blocked words
+	pub extra_blocked_words: Vec<String>, // Masked as well, even with filter_profanity off
+}
+
+impl Default for ChatConfig {
+	fn default() -> Self {
+		Self {
+			enabled: true,
+			messages_per_sec: 0.5,
+			burst: 4.0,
+			filter_profanity: true,
+			extra_blocked_words: Vec::new(),
+		}
+	}
+}
+
+impl ChatConfig {
+	// Trim and collapse whitespace, drop control characters, cut to MAX_CHAT_CHARS and mask
+	// blocked words. None if nothing is left.
+	pub fn clean(&self, raw: &str) -> Option<String> {
+		let text: String = raw.chars().filter(|c| !c.is_control()).take(MAX_CHAT_CHARS * 2).collect();
+		let words: Vec<String> = text.split_whitespace().map(|word| self.mask(word)).collect();
+		let text: String = words.join(" ").chars().take(MAX_CHAT_CHARS).collect();
+		let text = text.trim_end();
+		(!text.is_empty()).then(|| text.to_string())
+	}
+
+	fn mask(&self, word: &str) -> String {
+		let lower = word.to_lowercase();
+		let bare = lower.trim_start_matches(|c: char| !c.is_alphanumeric());
+		let builtin = BLOCKED_WORDS.iter().filter(|_| self.filter_profanity).copied();
+		let extra = self.extra_blocked_words.iter().map(String::as_str);
+		let blocked = builtin.chain(extra).any(|w| !w.is_empty() && bare.starts_with(&w.to_lowercase()));
+		if blocked {
+			"*".repeat(word.chars().count())
+		} else {
+			word.to_string()
+		}
+	}
+}
+
+// Per-connection chat rate limit and repeat check
+pub struct ChatThrottle {
+	enabled: bool,
+	rate: RateLimiter,
+	last: Option<(String, Instant)>,
+}
+
+impl ChatThrottle {
+	pub fn new(cfg: &ChatConfig) -> Self {
+		Self { enabled: cfg.enabled, rate: RateLimiter::new(cfg.messages_per_sec, cfg.burst), last: None }
+	}
+
+	// Whether `text` (already cleaned) may be sent now, or why not
+	pub fn check_message(&mut self, text: &str) -> Result<(), &'static str> {
+		if let Some((last, at)) = &self.last {
+			if last.eq_ignore_ascii_case(text) && at.elapsed() < REPEAT_WINDOW {
+				return Err("you just said that");
+			}
+		}
+		self.check_emote()?;
+		self.last = Some((text.to_string(), Instant::now()));
+		Ok(())
+	}
+
+	pub fn check_emote(&mut self) -> Result<(), &'static str> {
+		if !self.enabled {
+			Err("chat is turned off on this server")
+		} else if !self.rate.allow() {
+			Err("you're chatting too fast, slow down")
+		} else {
+			Ok(())
+		}
+	}
+}
@@ -0,0 +1,131 @@
+// Live connections by player (or spectator), so the admin API can reach them (send a message,
+// kick, find the address), and the IP addresses banned from connecting. Bans last until the
+// server restarts.
+
+use std::{
+	collections::{HashMap, HashSet},
+	net::IpAddr,
+	sync::{Arc, Mutex},
+};
+
+use axum::extract::ws::Message;
+use shared::{PlayerId, ServerToClient};
+use tokio::sync::mpsc;
+
+struct ClientHandle {
+	ip: IpAddr,
+	direct: mpsc::Sender<Message>, // The connection's writer
+	kick: mpsc::Sender<String>, // Reason; the connection closes and the player is removed
+	spectator: bool, // Watching without a truck
+}
+
+#[derive(Clone, Default)]
+pub struct Clients(Arc<Mutex<HashMap<PlayerId, ClientHandle>>>);
+
+impl Clients {
+	// Register the connection now playing `id` (replacing one it resumed from); the returned
+	// receiver gets a reason when an admin kicks it
+	pub fn register(&self, id: PlayerId, ip: IpAddr, direct: mpsc::Sender<Message>) -> mpsc::Receiver<String> {
+		let (kick, kicked) = mpsc::channel(1);
+		self.0.lock().unwrap().insert(id, ClientHandle { ip, direct, kick, spectator: false });
+		kicked
+	}
+
+	// The same for a new spectator watching as `id`, or None if `max` are watching already (0 = no limit).
+	// Counting and registering under one lock keeps simultaneous connects from going over.
+	pub fn register_spectator(
+		&self,
+		id: PlayerId,
+		ip: IpAddr,
+		direct: mpsc::Sender<Message>,
+		max: usize,
+	) -> Option<mpsc::Receiver<String>> {
+		let mut clients = self.0.lock().unwrap();
+		if max > 0 && clients.values().filter(|c| c.spectator).count() >= max {
+			return None;
+		}
+		let (kick, kicked) = mpsc::channel(1);
+		clients.insert(id, ClientHandle { ip, direct, kick, spectator: true });
+		Some(kicked)
+	}
+
+	// Forget the connection using `direct`, unless a newer one has taken over the player
+	pub fn unregister(&self, id: &PlayerId, direct: &mpsc::Sender<Message>) {
+		let mut clients = self.0.lock().unwrap();
+		if clients.get(id).is_some_and(|c| c.direct.same_channel(direct)) {
+			clients.remove(id);
+		}
+	}
+
+	pub fn ip(&self, id: &PlayerId) -> Option<IpAddr> {
+		self.0.lock().unwrap().get(id).map(|c| c.ip)
+	}
+
+	// Connected players and spectators, and their addresses
+	pub fn list(&self) -> HashMap<PlayerId, IpAddr> {
+		self.0.lock().unwrap().iter().map(|(id, c)| (*id, c.ip)).collect()
+	}
+
+	// Connected spectators and their addresses
+	pub fn spectators(&self) -> HashMap<PlayerId, IpAddr> {
+		self.0.lock().unwrap().iter().filter(|(_, c)| c.spectator).map(|(id, c)| (*id, c.ip)).collect()
+	}
+
+	pub fn spectator_count(&self) -> usize {
+		self.0.lock().unwrap().values().filter(|c| c.spectator).count()
+	}
+
+	// Close the player's or spectator's connection; false if it has none
+	pub fn kick(&self, id: &PlayerId, reason: &str) -> bool {
+		match self.0.lock().unwrap().get(id) {
+			Some(client) => {
+				// Full only if it is being kicked already
+				let _ = client.kick.try_send(reason.to_string());
+				true
+			}
+			None => false,
+		}
+	}
+
+	// Close every connection from `ip`; returns the players kicked
+	pub fn kick_ip(&self, ip: IpAddr, reason: &str) -> Vec<PlayerId> {
+		let clients = self.0.lock().unwrap();
+		let kicked: Vec<PlayerId> = clients.iter().filter(|(_, c)| c.ip == ip).map(|(id, _)| *id).collect();
+		for id in &kicked {
+			let _ = clients[id].kick.try_send(reason.to_string());
+		}
+		kicked
+	}
+
+	// Queue `msg` for every connected client; returns how many it was sent to
+	pub fn send_all(&self, msg: &ServerToClient) -> usize {
+		let text = serde_json::to_string(msg).unwrap();
+		let clients = self.0.lock().unwrap();
+		clients.values().filter(|c| c.direct.try_send(Message::Text(text.clone())).is_ok()).count()
+	}
+}
+
+#[derive(Clone, Default)]
+pub struct Bans(Arc<Mutex<HashSet<IpAddr>>>);
+
+impl Bans {
+	pub fn contains(&self, ip: &IpAddr) -> bool {
+		self.0.lock().unwrap().contains(ip)
+	}
+
+	// False if it was already banned
+	pub fn add(&self, ip: IpAddr) -> bool {
+		self.0.lock().unwrap().insert(ip)
+	}
+
+	// False if it wasn't banned
+	pub fn remove(&self, ip: &IpAddr) -> bool {
+		self.0.lock().unwrap().remove(ip)
+	}
+
+	pub fn list(&self) -> Vec<IpAddr> {
+		let mut ips: Vec<IpAddr> = self.0.lock().unwrap().iter().copied().collect();
+		ips.sort();
+		ips
+	}
+}
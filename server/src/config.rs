@@ -0,0 +1,273 @@
+// Server settings in layers: built-in defaults, then a TOML file (`--config` or CONFIG_FILE),
+// then environment variables, then command-line flags. The layers are merged into one TOML
+// table before it is deserialized, so a setting is parsed and checked the same way whichever
+// layer it came from. See server/config.example.toml for every setting.
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Context};
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+use shared::{BotDifficulty, GameConfig};
+use toml::{Table, Value};
+
+use crate::{chat::ChatConfig, limits::Limits, profiles::ProfilesConfig};
+
+#[derive(Parser)]
+#[command(about = "Hover Truck game server")]
+pub struct Cli {
+	/// TOML config file (default: $CONFIG_FILE, if set)
+	#[arg(long, short)]
+	config: Option<PathBuf>,
+	/// Port to listen on
+	#[arg(long)]
+	port: Option<u16>,
+	/// Arena to play on (JSON or RON)
+	#[arg(long)]
+	map: Option<PathBuf>,
+	/// Bots fill the room up to this many trucks (0 = no bots)
+	#[arg(long)]
+	bot_target: Option<usize>,
+	/// Difficulty of new bots: easy, normal or hard
+	#[arg(long)]
+	bot_difficulty: Option<String>,
+	/// Simulation ticks per second
+	#[arg(long)]
+	tick_rate: Option<u32>,
+	/// Override any setting, e.g. --set gameplay.player_speed=14 (repeatable)
+	#[arg(long = "set", value_name = "KEY=VALUE")]
+	set: Vec<String>,
+	/// Print the resulting configuration as TOML and exit
+	#[arg(long)]
+	pub print_config: bool,
+}
+
+impl Cli {
+	pub fn config_file(&self) -> Option<PathBuf> {
+		self.config.clone().or_else(|| std::env::var_os("CONFIG_FILE").map(PathBuf::from))
+	}
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+	pub network: NetworkConfig,
+	pub limits: Limits,
+	pub room: RoomConfig,
+	pub chat: ChatConfig,
+	pub profiles: ProfilesConfig,
+	pub gameplay: GameConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkConfig {
+	pub port: u16,
+	pub broadcast_capacity: usize, // State snapshots queued per client before it counts as lagging
+	pub admin_token: String, // Bearer token for the admin API; empty disables it
+	pub resume_grace_secs: f64, // How long a dropped player's truck waits for them to reconnect (0 = no resuming)
+	pub shutdown_drain_secs: u32, // Between SIGTERM and closing every connection
+}
+
+impl Default for NetworkConfig {
+	fn default() -> Self {
+		Self {
+			port: 4001,
+			broadcast_capacity: 64,
+			admin_token: String::new(),
+			resume_grace_secs: 30.0,
+			shutdown_drain_secs: 5,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RoomConfig {
+	pub map_file: Option<PathBuf>, // Arena definition (JSON or RON); empty square world if unset
+	pub bot_target: usize, // Bots fill the room up to this many trucks and leave as humans join (0 = no bots)
+	pub bot_difficulty: String, // easy, normal or hard
+	pub standings_file: String, // Final standings are written here on shutdown; empty to not save them
+}
+
+impl Default for RoomConfig {
+	fn default() -> Self {
+		Self {
+			map_file: None,
+			bot_target: 4,
+			bot_difficulty: "normal".to_string(),
+			standings_file: "standings.json".to_string(),
+		}
+	}
+}
+
+impl RoomConfig {
+	pub fn difficulty(&self) -> BotDifficulty {
+		self.bot_difficulty.parse().unwrap_or_default()
+	}
+}
+
+// Environment variables and the setting each one overrides
+const ENV_VARS: &[(&str, &str)] = &[
+	("PORT", "network.port"),
+	("BROADCAST_CAPACITY", "network.broadcast_capacity"),
+	("ADMIN_TOKEN", "network.admin_token"),
+	("RESUME_GRACE_SECS", "network.resume_grace_secs"),
+	("SHUTDOWN_DRAIN_SECS", "network.shutdown_drain_secs"),
+	("WS_MAX_MESSAGE_BYTES", "limits.max_message_bytes"),
+	("WS_MESSAGES_PER_SEC", "limits.messages_per_sec"),
+	("WS_MESSAGE_BURST", "limits.message_burst"),
+	("WS_MAX_PROTOCOL_ERRORS", "limits.max_protocol_errors"),
+	("WS_PROTOCOL_ERROR_WINDOW_SECS", "limits.protocol_error_window_secs"),
+	("MAX_CONNECTIONS_PER_IP", "limits.max_connections_per_ip"),
+	("MAX_PLAYERS", "limits.max_players"),
+	("MAX_SPECTATORS", "limits.max_spectators"),
+	("WS_MAX_BEHIND_SECS", "limits.max_behind_secs"),
+	("WS_SEND_TIMEOUT_SECS", "limits.send_timeout_secs"),
+	("MAP_FILE", "room.map_file"),
+	("BOT_TARGET", "room.bot_target"),
+	("BOT_DIFFICULTY", "room.bot_difficulty"),
+	("STANDINGS_FILE", "room.standings_file"),
+	("CHAT_ENABLED", "chat.enabled"),
+	("PROFILES_DB", "profiles.database"),
+	("PROFILES_NEW_PER_IP_PER_HOUR", "profiles.new_per_ip_per_hour"),
+	("TICK_RATE", "gameplay.tick_rate"),
+];
+
+impl ServerConfig {
+	pub fn load(cli: &Cli) -> anyhow::Result<Self> {
+		let defaults = Table::try_from(ServerConfig::default()).expect("default config serializes");
+
+		let path = cli.config_file();
+		let mut table = match &path {
+			Some(path) => {
+				let text = std::fs::read_to_string(path)
+					.with_context(|| format!("failed to read config file {}", path.display()))?;
+				text.parse::<Table>().with_context(|| format!("invalid config file {}", path.display()))?
+			}
+			None => Table::new(),
+		};
+
+		for (var, key) in ENV_VARS {
+			if let Ok(raw) = std::env::var(var) {
+				set_key(&mut table, &defaults, key, &raw).with_context(|| format!("invalid {var}"))?;
+			}
+		}
+
+		let flags = [
+			("network.port", cli.port.map(|v| v.to_string())),
+			("room.map_file", cli.map.as_ref().map(|v| v.display().to_string())),
+			("room.bot_target", cli.bot_target.map(|v| v.to_string())),
+			("room.bot_difficulty", cli.bot_difficulty.clone()),
+			("gameplay.tick_rate", cli.tick_rate.map(|v| v.to_string())),
+		];
+		for (key, raw) in flags {
+			if let Some(raw) = raw {
+				set_key(&mut table, &defaults, key, &raw)?;
+			}
+		}
+		for setting in &cli.set {
+			let Some((key, raw)) = setting.split_once('=') else {
+				bail!("--set expects KEY=VALUE, got {setting:?}");
+			};
+			set_key(&mut table, &defaults, key.trim(), raw.trim()).with_context(|| format!("invalid --set {setting}"))?;
+		}
+
+		// GameConfig is also sent to clients, so it can't deny unknown fields itself
+		let mut errors = Vec::new();
+		if let (Some(Value::Table(gameplay)), Some(Value::Table(known))) = (table.get("gameplay"), defaults.get("gameplay")) {
+			for key in gameplay.keys().filter(|k| !known.contains_key(*k)) {
+				errors.push(format!("unknown setting gameplay.{key}"));
+			}
+		}
+		let config: ServerConfig = table.try_into().context("invalid configuration")?;
+		if let Err(mut invalid) = config.validate() {
+			errors.append(&mut invalid);
+		}
+		if !errors.is_empty() {
+			bail!("invalid configuration:\n  {}", errors.join("\n  "));
+		}
+		Ok(config)
+	}
+
+	// Every out-of-range setting, as "section.setting must ..." messages
+	fn validate(&self) -> Result<(), Vec<String>> {
+		let mut errors = Vec::new();
+		let net = &self.network;
+		if net.broadcast_capacity == 0 {
+			errors.push("network.broadcast_capacity must be at least 1".to_string());
+		}
+		if !(net.resume_grace_secs.is_finite() && net.resume_grace_secs >= 0.0) {
+			errors.push(format!("network.resume_grace_secs must be zero or more (got {})", net.resume_grace_secs));
+		}
+
+		let limits = &self.limits;
+		if limits.max_message_bytes < 256 {
+			errors.push(format!("limits.max_message_bytes must be at least 256 (got {})", limits.max_message_bytes));
+		}
+		let mut positive = |name: &str, v: f64| {
+			if !(v.is_finite() && v > 0.0) {
+				errors.push(format!("limits.{name} must be a positive number (got {v})"));
+			}
+		};
+		positive("messages_per_sec", limits.messages_per_sec);
+		positive("protocol_error_window_secs", limits.protocol_error_window_secs);
+		positive("max_behind_secs", limits.max_behind_secs);
+		positive("send_timeout_secs", limits.send_timeout_secs);
+		if limits.message_burst.is_nan() || limits.message_burst < 1.0 {
+			errors.push(format!("limits.message_burst must be at least 1 (got {})", limits.message_burst));
+		}
+		if limits.max_protocol_errors == 0 {
+			errors.push("limits.max_protocol_errors must be at least 1".to_string());
+		}
+		if limits.max_connections_per_ip == 0 {
+			errors.push("limits.max_connections_per_ip must be at least 1".to_string());
+		}
+
+		let chat = &self.chat;
+		if !(chat.messages_per_sec.is_finite() && chat.messages_per_sec > 0.0) {
+			errors.push(format!("chat.messages_per_sec must be a positive number (got {})", chat.messages_per_sec));
+		}
+		if chat.burst.is_nan() || chat.burst < 1.0 {
+			errors.push(format!("chat.burst must be at least 1 (got {})", chat.burst));
+		}
+
+		if let Err(e) = self.room.bot_difficulty.parse::<BotDifficulty>() {
+			errors.push(format!("room.bot_difficulty: {e}"));
+		}
+		if let Err(invalid) = self.gameplay.validate() {
+			errors.extend(invalid.into_iter().map(|e| format!("gameplay.{e}")));
+		}
+		if errors.is_empty() { Ok(()) } else { Err(errors) }
+	}
+}
+
+// Set a dotted `key` from a raw string. Strings stay strings; anything else is parsed as a
+// TOML value so numbers and booleans get their types.
+fn set_key(table: &mut Table, defaults: &Table, key: &str, raw: &str) -> anyhow::Result<()> {
+	let path: Vec<&str> = key.split('.').collect();
+	let [section, name] = path[..] else {
+		bail!("unknown setting {key:?} (expected section.setting, e.g. gameplay.tick_rate)");
+	};
+	let Some(Value::Table(known)) = defaults.get(section) else {
+		bail!("unknown config section {section:?} in {key:?}");
+	};
+	let value = match known.get(name) {
+		Some(Value::String(_)) => Value::String(raw.to_string()),
+		// Optional settings like room.map_file have no default; unknown ones are rejected when
+		// the table is deserialized
+		None if section != "gameplay" => Value::String(raw.to_string()),
+		None => bail!("unknown setting {key:?}"),
+		Some(default) => match format!("v = {raw}").parse::<Table>().ok().and_then(|mut t| t.remove("v")) {
+			Some(Value::Integer(i)) if default.is_float() => Value::Float(i as f64),
+			Some(v) => v,
+			None => bail!("expected {} value for {key}, got {raw:?}", default.type_str()),
+		},
+	};
+	let section = table.entry(section).or_insert_with(|| Value::Table(Table::new()));
+	let Value::Table(section) = section else {
+		bail!("config section {:?} is not a table", path[0]);
+	};
+	section.insert(name.to_string(), value);
+	Ok(())
+}
@@ -0,0 +1,125 @@
+// Protection for the WebSocket endpoint so one misbehaving client can't stall the tick loop:
+// message size and rate limits per connection, a budget of protocol errors over a time window
+// before the connection is dropped, caps on players and on simultaneous connections from one
+// IP, and how long a slow client may fall behind the state broadcast.
+
+use std::{
+	collections::HashMap,
+	net::IpAddr,
+	sync::{Arc, Mutex},
+	time::Instant,
+};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Limits {
+	pub max_message_bytes: usize,
+	pub messages_per_sec: f64, // Sustained rate; the client sends inputs at 20 Hz plus pings
+	pub message_burst: f64,
+	// Budget of malformed, binary or rate-limited messages: a client is disconnected once it
+	// goes over max_protocol_errors within protocol_error_window_secs. The budget refills
+	// steadily, so the odd error over a long session never adds up to a disconnect.
+	pub max_protocol_errors: u32,
+	pub protocol_error_window_secs: f64,
+	pub max_connections_per_ip: usize,
+	pub max_players: usize, // Human players (including ones waiting to resume); 0 = unlimited
+	pub max_spectators: usize, // Connections watching without a truck; 0 = unlimited
+	pub max_behind_secs: f64, // Disconnect clients that can't keep up with state broadcasts for this long
+	pub send_timeout_secs: f64, // Disconnect if a single send to the client blocks this long
+}
+
+impl Default for Limits {
+	fn default() -> Self {
+		Self {
+			max_message_bytes: 4096,
+			messages_per_sec: 60.0,
+			message_burst: 120.0,
+			max_protocol_errors: 20,
+			protocol_error_window_secs: 60.0,
+			max_connections_per_ip: 8,
+			max_players: 32,
+			max_spectators: 64,
+			max_behind_secs: 5.0,
+			send_timeout_secs: 10.0,
+		}
+	}
+}
+
+// Token bucket: `burst` messages at once, refilled at `rate` per second
+pub struct RateLimiter {
+	tokens: f64,
+	rate: f64,
+	burst: f64,
+	last: Instant,
+}
+
+impl RateLimiter {
+	pub fn new(rate: f64, burst: f64) -> Self {
+		Self { tokens: burst, rate, burst, last: Instant::now() }
+	}
+
+	// Take a token; false if the connection is over its rate
+	pub fn allow(&mut self) -> bool {
+		self.refill();
+		if self.tokens >= 1.0 {
+			self.tokens -= 1.0;
+			true
+		} else {
+			false
+		}
+	}
+
+	// Refilled to the full burst, so forgetting it would change nothing
+	pub fn is_full(&mut self) -> bool {
+		self.refill();
+		self.tokens >= self.burst
+	}
+
+	fn refill(&mut self) {
+		let now = Instant::now();
+		self.tokens = (self.tokens + now.duration_since(self.last).as_secs_f64() * self.rate).min(self.burst);
+		self.last = now;
+	}
+}
+
+// Live WebSocket connections per client IP
+#[derive(Clone, Default)]
+pub struct ConnectionCounter(Arc<Mutex<HashMap<IpAddr, usize>>>);
+
+impl ConnectionCounter {
+	// Live connections from all addresses
+	pub fn total(&self) -> usize {
+		self.0.lock().unwrap().values().sum()
+	}
+
+	// Reserve a connection slot for `ip`, or None if it already has `max` connections.
+	// The slot is released when the returned guard is dropped.
+	pub fn acquire(&self, ip: IpAddr, max: usize) -> Option<ConnectionSlot> {
+		let mut counts = self.0.lock().unwrap();
+		let count = counts.entry(ip).or_default();
+		if *count >= max {
+			return None;
+		}
+		*count += 1;
+		Some(ConnectionSlot { counter: self.clone(), ip })
+	}
+}
+
+pub struct ConnectionSlot {
+	counter: ConnectionCounter,
+	ip: IpAddr,
+}
+
+impl Drop for ConnectionSlot {
+	fn drop(&mut self) {
+		let mut counts = self.counter.0.lock().unwrap();
+		if let Some(count) = counts.get_mut(&self.ip) {
+			*count -= 1;
+			if *count == 0 {
+				counts.remove(&self.ip);
+			}
+		}
+	}
+}
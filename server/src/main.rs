@@ -1,152 +1,805 @@
-use std::{net::SocketAddr, sync::Arc, time::Duration};
+use std::{
+	collections::HashMap,
+	net::SocketAddr,
+	sync::{atomic::Ordering::Relaxed, Arc},
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use axum::{
 	extract::{
-		ws::{Message, WebSocket},
-		State, WebSocketUpgrade,
+		ws::{close_code, CloseFrame, Message, WebSocket},
+		ConnectInfo, Query, State, WebSocketUpgrade,
 	},
+	http::StatusCode,
 	response::IntoResponse,
 	routing::get,
 	Router,
 };
-use futures::{sink::SinkExt, stream::StreamExt};
-use shared::{ClientToServer, GameConfig, GameSim, ServerToClient};
-use tokio::sync::{broadcast, mpsc, Mutex};
-use tracing::{error, info};
+use anyhow::Context;
+use clap::Parser;
+use futures::{
+	sink::SinkExt,
+	stream::{SplitSink, StreamExt},
+};
+use serde::{Deserialize, Serialize};
+use shared::{
+	ClientToServer, GameConfig, GameSim, MapDef, PlayerId, ServerToClient, CLOSE_KICKED, CLOSE_SERVER_FULL,
+	CLOSE_VERSION_MISMATCH, MAX_NAME_CHARS,
+	PROTOCOL_VERSION,
+};
+use tokio::sync::{broadcast, mpsc, watch, Mutex};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{
+	chat::{ChatConfig, ChatThrottle},
+	clients::{Bans, Clients},
+	config::{Cli, ServerConfig},
+	limits::{ConnectionCounter, ConnectionSlot, Limits, RateLimiter},
+	metrics::Metrics,
+	profiles::Profiles,
+	session::Sessions,
+};
+
+mod admin;
+mod chat;
+mod clients;
+mod config;
+mod limits;
+mod metrics;
+mod profiles;
+mod reload;
+mod session;
+
+// Latest (steer, boost) from each connection, applied by the tick loop. Kept outside the sim
+// mutex so reading client input never waits on a tick.
+type InputMailbox = Arc<std::sync::Mutex<HashMap<PlayerId, (f32, bool)>>>;
 
 #[derive(Clone)]
 struct AppState {
 	sim: Arc<Mutex<GameSim>>,
 	tx_state: broadcast::Sender<String>,
+	admin_token: Option<Arc<str>>,
+	inputs: InputMailbox,
+	limits: Arc<Limits>,
+	connections: ConnectionCounter,
+	sessions: Sessions,
+	shutdown: watch::Receiver<ShutdownPhase>,
+	cli: Arc<Cli>, // Kept to load the configuration again on reload
+	config: Arc<ServerConfig>, // As loaded at startup
+	gameplay: Arc<watch::Sender<GameConfig>>, // The sim's current tuning, for connections to pass on when it changes
+	metrics: Arc<Metrics>,
+	clients: Clients,
+	bans: Bans,
+	chat: Arc<ChatConfig>,
+	profiles: Option<Arc<Profiles>>, // None unless profiles.database is set
 }
 
+// Where a graceful shutdown has got to. While draining, new connections are refused and
+// players are warned; on closing every connection is closed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ShutdownPhase {
+	Running,
+	Draining { seconds: u32 },
+	Closing,
+}
+
+const SHUTDOWN_REASON: &str = "server shutting down";
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+	let cli = Cli::parse();
+	let config = ServerConfig::load(&cli)?;
+	if cli.print_config {
+		print!("{}", toml::to_string_pretty(&config)?);
+		return Ok(());
+	}
+
 	// Initialize logging first
 	tracing_subscriber::fmt().with_env_filter("info").init();
 	// Log immediately to verify logging works
 	eprintln!("Server starting...");
 	info!("Server starting with tracing initialized");
 
-	let mut sim = GameSim::new(GameConfig::default());
-	// Spawn some bots at startup
-	for _ in 0..3 {
-		sim.add_bot();
-	}
-	let (tx_state, _rx_state) = broadcast::channel::<String>(64);
-	let state = AppState { sim: Arc::new(Mutex::new(sim)), tx_state };
+	// Optional arena definition (JSON or RON); defaults to the empty square world
+	let mut sim = match &config.room.map_file {
+		Some(path) => {
+			let map = MapDef::load(path).map_err(|e| anyhow::anyhow!("failed to load map {}: {e}", path.display()))?;
+			info!("loaded map '{}' from {} ({} obstacles)", map.name, path.display(), map.obstacles.len());
+			GameSim::with_map(config.gameplay.clone(), map)
+		}
+		None => GameSim::new(config.gameplay.clone()),
+	};
+	// Bots fill the room up to bot_target trucks and make room as humans join
+	sim.target_trucks = config.room.bot_target;
+	sim.bot_difficulty = config.room.difficulty();
+	sim.balance_bots();
+	let (tx_state, _rx_state) = broadcast::channel::<String>(config.network.broadcast_capacity);
+	let admin_token = Some(config.network.admin_token.as_str()).filter(|t| !t.is_empty()).map(Arc::from);
+	info!("connection limits: {:?}", config.limits);
+	let drain_secs = config.network.shutdown_drain_secs;
+	let port = config.network.port;
+	let (shutdown_tx, shutdown_rx) = watch::channel(ShutdownPhase::Running);
+	let (gameplay_tx, _) = watch::channel(sim.cfg.clone());
+	let profiles = match &config.profiles.database {
+		Some(path) => Some(Arc::new(
+			Profiles::open(path, config.profiles.new_per_ip_per_hour).with_context(|| format!("failed to open profile database {}", path.display()))?,
+		)),
+		None => None,
+	};
+	let state = AppState {
+		sim: Arc::new(Mutex::new(sim)),
+		tx_state,
+		admin_token,
+		inputs: InputMailbox::default(),
+		limits: Arc::new(config.limits.clone()),
+		connections: ConnectionCounter::default(),
+		sessions: Sessions::new(Duration::from_secs_f64(config.network.resume_grace_secs)),
+		shutdown: shutdown_rx,
+		chat: Arc::new(config.chat.clone()),
+		cli: Arc::new(cli),
+		config: Arc::new(config),
+		gameplay: Arc::new(gameplay_tx),
+		metrics: Arc::new(Metrics::new()),
+		clients: Clients::default(),
+		bans: Bans::default(),
+		profiles,
+	};
 
 	let app = Router::new()
 		.route("/ws", get(ws_handler))
+		.merge(admin::router())
+		.merge(metrics::router())
+		.merge(profiles::router())
 		.with_state(state.clone());
 
-	// Tick loop - 30 TPS to reduce stuttering with higher speeds
+	// Gameplay tuning is reloaded whenever the config file changes (and on POST /admin/config/reload)
+	if let Some(path) = state.cli.config_file() {
+		tokio::spawn(reload::watch_file(state.clone(), path));
+	}
+	if let Some(profiles) = &state.profiles {
+		tokio::spawn(profiles::flush_loop(profiles.clone()));
+	}
+
+	// Tick loop: fixed timestep at cfg.tick_rate. Deadlines advance by exactly one tick so the
+	// loop doesn't drift; if we fall behind, run up to MAX_CATCH_UP_TICKS steps back to back
+	// and drop anything beyond that rather than spiralling.
 	let state_for_tick = state.clone();
 	tokio::spawn(async move {
-		let mut ticker = tokio::time::interval(Duration::from_millis(33));
+		let metrics = &state_for_tick.metrics;
+		const MAX_CATCH_UP_TICKS: u32 = 5;
+		let mut tick_rate = state_for_tick.sim.lock().await.cfg.tick_rate.max(1);
+		let mut tick = Duration::from_secs_f64(1.0 / tick_rate as f64);
+		let mut next_tick = tokio::time::Instant::now() + tick;
 		loop {
-			ticker.tick().await;
+			tokio::time::sleep_until(next_tick).await;
 			let mut sim = state_for_tick.sim.lock().await;
-			sim.step();
-			// Sync bot info to world state before sending to clients
+			// A reload may have changed the tick rate; the next deadline uses the new one
+			if sim.cfg.tick_rate.max(1) != tick_rate {
+				tick_rate = sim.cfg.tick_rate.max(1);
+				tick = Duration::from_secs_f64(1.0 / tick_rate as f64);
+			}
+			let now = tokio::time::Instant::now();
+			let mut steps = 0;
+			while now >= next_tick && steps < MAX_CATCH_UP_TICKS {
+				for (id, (steer, boost)) in state_for_tick.inputs.lock().unwrap().drain() {
+					sim.submit_steer(id, steer);
+					sim.submit_boost(id, boost);
+				}
+				let started = std::time::Instant::now();
+				sim.step();
+				metrics.record_tick(started.elapsed(), tick);
+				if let Some(profiles) = &state_for_tick.profiles {
+					profiles.observe(&sim);
+				}
+				next_tick += tick;
+				steps += 1;
+			}
+			if now >= next_tick {
+				let behind = (now - next_tick).as_secs_f64() * tick_rate as f64;
+				warn!("tick loop fell behind, skipping {behind:.0} ticks");
+				metrics.ticks_skipped.fetch_add(behind.round() as u64, Relaxed);
+				next_tick = now + tick;
+			}
+			// Sync bot info and the audience to world state before sending to clients
+			let spectators = state_for_tick.clients.spectator_count();
 			let mut world_state = sim.state.clone();
 			world_state.bots = sim.bots.clone();
+			world_state.spectators = spectators as u32;
 			let world = serde_json::to_string(&ServerToClient::State(world_state));
+			metrics.players.store(sim.state.players.len() as u64, Relaxed);
+			metrics.bots.store(sim.bots.len() as u64, Relaxed);
+			metrics.spectators.store(spectators as u64, Relaxed);
 			if let Ok(json) = world {
+				metrics.record_snapshot(json.len());
 				let _ = state_for_tick.tx_state.send(json);
 			}
 		}
 	});
 
-	let port = std::env::var("PORT").ok().and_then(|p| p.parse().ok()).unwrap_or(4001u16);
 	let addr = SocketAddr::from(([0, 0, 0, 0], port));
 	info!("server listening on {addr}");
 	let listener = tokio::net::TcpListener::bind(addr).await?;
-	axum::serve(listener, app).await?;
+	axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+		.with_graceful_shutdown(drain(state.clone(), shutdown_tx, drain_secs))
+		.await?;
+
+	// The listener is closed; give the WebSocket connections a moment to send their close frames
+	let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+	while state.connections.total() > 0 && tokio::time::Instant::now() < deadline {
+		tokio::time::sleep(Duration::from_millis(50)).await;
+	}
+	if let Some(profiles) = &state.profiles {
+		profiles.flush().await;
+	}
+	info!("server stopped");
 	Ok(())
 }
 
-async fn ws_handler(ws: WebSocketUpgrade, State(state): State<AppState>) -> impl IntoResponse {
-	ws.on_upgrade(move |socket| client_connection(socket, state))
+// Resolves once the server should stop accepting connections: after SIGTERM or Ctrl-C, warn
+// the players, keep the game running for `drain_secs`, then end the match (showing the players
+// the final scoreboard and saving it) and tell every connection to close.
+async fn drain(state: AppState, shutdown: watch::Sender<ShutdownPhase>, drain_secs: u32) {
+	wait_for_signal().await;
+	info!("shutdown requested, closing connections in {drain_secs}s");
+	let _ = shutdown.send(ShutdownPhase::Draining { seconds: drain_secs });
+	tokio::time::sleep(Duration::from_secs(drain_secs as u64)).await;
+	let standings = FinalStandings::new(&*state.sim.lock().await, state.profiles.as_deref());
+	standings.log();
+	state.clients.send_all(&ServerToClient::ServerMessage(standings.scoreboard()));
+	standings.save(&state.config.room.standings_file).await;
+	info!("closing {} connections", state.connections.total());
+	let _ = shutdown.send(ShutdownPhase::Closing);
+}
+
+async fn wait_for_signal() {
+	let ctrl_c = async {
+		if let Err(e) = tokio::signal::ctrl_c().await {
+			warn!("can't listen for Ctrl-C: {e}");
+			std::future::pending::<()>().await;
+		}
+	};
+	#[cfg(unix)]
+	let terminate = async {
+		match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+			Ok(mut sigterm) => {
+				sigterm.recv().await;
+			}
+			Err(e) => {
+				warn!("can't listen for SIGTERM: {e}");
+				std::future::pending::<()>().await;
+			}
+		}
+	};
+	#[cfg(not(unix))]
+	let terminate = std::future::pending::<()>();
+	tokio::select! {
+		_ = ctrl_c => {}
+		_ = terminate => {}
+	}
+}
+
+// Every truck ranked by trailer length when the server stopped. The game runs continuously, so
+// this is the closest thing it has to a match result.
+#[derive(Serialize)]
+struct FinalStandings {
+	ended_at: u64, // Unix seconds
+	tick: u64,
+	standings: Vec<Standing>,
+}
+
+#[derive(Serialize)]
+struct Standing {
+	rank: usize,
+	player: PlayerId,
+	bot: bool,
+	trailer: usize,
+	profile: Option<Uuid>, // The profile the truck played for, if profiles are on
+}
+
+// Trucks named on the final scoreboard
+const SCOREBOARD_LEN: usize = 5;
+
+impl FinalStandings {
+	fn new(sim: &GameSim, profiles: Option<&Profiles>) -> Self {
+		let mut players: Vec<_> = sim.state.players.values().collect();
+		players.sort_by_key(|p| (std::cmp::Reverse(p.trailer.len()), p.id));
+		let standings = players
+			.iter()
+			.enumerate()
+			.map(|(i, player)| Standing {
+				rank: i + 1,
+				player: player.id,
+				bot: sim.bots.contains(&player.id),
+				trailer: player.trailer.len(),
+				profile: profiles.and_then(|profiles| profiles.linked(&player.id)),
+			})
+			.collect();
+		let ended_at = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+		Self { ended_at, tick: sim.state.tick, standings }
+	}
+
+	fn log(&self) {
+		info!("final standings at tick {} ({} trucks)", self.tick, self.standings.len());
+		for s in &self.standings {
+			let kind = if s.bot { "bot" } else { "human" };
+			info!("  {}. {} ({kind}) trailer {}", s.rank, s.player, s.trailer);
+		}
+	}
+
+	// The top of the table, for the players' announcement before they are disconnected
+	fn scoreboard(&self) -> String {
+		if self.standings.is_empty() {
+			return "Match over".to_string();
+		}
+		let top: Vec<String> = self
+			.standings
+			.iter()
+			.take(SCOREBOARD_LEN)
+			.map(|s| {
+				let bot = if s.bot { " (bot)" } else { "" };
+				format!("{}. Truck {}{bot} trailer {}", s.rank, &s.player.simple().to_string()[..4], s.trailer)
+			})
+			.collect();
+		format!("Match over! {}", top.join("; "))
+	}
+
+	// Write the standings to `path` as JSON, unless it is empty
+	async fn save(&self, path: &str) {
+		if path.is_empty() {
+			return;
+		}
+		let json = match serde_json::to_string_pretty(self) {
+			Ok(json) => json,
+			Err(e) => {
+				warn!("failed to serialize final standings: {e}");
+				return;
+			}
+		};
+		match tokio::fs::write(path, json).await {
+			Ok(()) => info!("final standings saved to {path}"),
+			Err(e) => warn!("failed to save final standings to {path}: {e}"),
+		}
+	}
+}
+
+#[derive(Deserialize)]
+struct WsParams {
+	v: Option<u32>, // Client's PROTOCOL_VERSION; older clients don't send it
+	resume: Option<String>, // Session token from an earlier Welcome
+	#[serde(default)]
+	spectate: bool, // Watch without a truck
+	profile: Option<String>, // Token of the player's profile, if the server keeps them
+}
+
+async fn ws_handler(
+	ws: WebSocketUpgrade,
+	ConnectInfo(addr): ConnectInfo<SocketAddr>,
+	Query(params): Query<WsParams>,
+	State(state): State<AppState>,
+) -> impl IntoResponse {
+	if *state.shutdown.borrow() != ShutdownPhase::Running {
+		return (StatusCode::SERVICE_UNAVAILABLE, SHUTDOWN_REASON).into_response();
+	}
+	let Some(slot) = state.connections.acquire(addr.ip(), state.limits.max_connections_per_ip) else {
+		warn!("rejecting connection from {addr}: too many connections from this address");
+		return (StatusCode::TOO_MANY_REQUESTS, "too many connections from this address").into_response();
+	};
+	let max = state.limits.max_message_bytes;
+	ws.max_message_size(max)
+		.max_frame_size(max)
+		.on_upgrade(move |socket| client_connection(socket, state, addr, params, slot))
+		.into_response()
 }
 
-async fn client_connection(mut socket: WebSocket, state: AppState) {
+async fn client_connection(
+	socket: WebSocket,
+	state: AppState,
+	addr: SocketAddr,
+	params: WsParams,
+	_slot: ConnectionSlot,
+) {
 	let (mut sink, mut stream) = socket.split();
+	if let Some(v) = params.v.filter(|&v| v != PROTOCOL_VERSION) {
+		info!("turning away {addr}: client protocol {v}, server {PROTOCOL_VERSION}");
+		let reason = format!("server protocol {PROTOCOL_VERSION}, client protocol {v}");
+		let _ = sink.send(Message::Close(Some(CloseFrame { code: CLOSE_VERSION_MISMATCH, reason: reason.into() }))).await;
+		return;
+	}
+	if state.bans.contains(&addr.ip()) {
+		info!("turning away {addr}: banned");
+		let _ = sink.send(Message::Close(Some(CloseFrame { code: CLOSE_KICKED, reason: "banned from this server".into() }))).await;
+		return;
+	}
 	let mut rx_broadcast = state.tx_state.subscribe();
+	let mut gameplay = state.gameplay.subscribe();
 	let (tx_direct, mut rx_direct) = mpsc::channel::<Message>(16);
 
-	// On connect: add player and send welcome
-	let player_id = {
+	// On connect: take back the truck of a resumable session, or add a new player (spectators
+	// get an id of their own and no truck or session); then register the connection and welcome
+	let spectator = params.spectate;
+	let (player_id, session, mut kicked) = if spectator {
+		// The spectator's place is taken as it is checked for
+		let id = PlayerId::new_v4();
+		let max = state.limits.max_spectators;
+		let Some(kicked) = state.clients.register_spectator(id, addr.ip(), tx_direct.clone(), max) else {
+			info!("turning away {addr}: too many spectators ({max})");
+			let close = CloseFrame { code: CLOSE_SERVER_FULL, reason: "too many spectators".into() };
+			let _ = sink.send(Message::Close(Some(close))).await;
+			return;
+		};
+		// Snapshot what the spectator needs under the sim lock, then send it without the lock
+		let welcome = {
+			let sim = state.sim.lock().await;
+			ServerToClient::Welcome {
+				id,
+				world_size: sim.cfg.world_size,
+				map: sim.map.clone(),
+				config: sim.cfg.clone(),
+				session: String::new(),
+				resumed: false,
+				spectator: true,
+				profile: None,
+			}
+		};
+		if !send_welcome(&mut sink, &welcome, &state).await {
+			info!("dropping spectator {addr}: couldn't send Welcome");
+			state.clients.unregister(&id, &tx_direct);
+			return;
+		}
+		info!("{addr} is spectating as {id}");
+		(id, None, kicked)
+	} else {
+		// Sign in before taking the sim lock; the database is slower than a tick
+		let mut profile = match (&state.profiles, params.profile) {
+			(Some(profiles), Some(token)) => profiles.sign_in(token, addr.ip()).await,
+			_ => None,
+		};
 		let mut sim = state.sim.lock().await;
-		let id = sim.add_player();
-		let welcome = ServerToClient::Welcome { id, world_size: sim.cfg.world_size };
-		let _ = sink.send(Message::Text(serde_json::to_string(&welcome).unwrap())).await;
-		id
+		let resumed = params.resume.and_then(|token| {
+			let id = state.sessions.resume(&token)?;
+			if sim.state.players.contains_key(&id) {
+				Some((id, token))
+			} else {
+				state.sessions.end(&token);
+				None
+			}
+		});
+		let is_resumed = resumed.is_some();
+		let (id, session) = match resumed {
+			Some((id, token)) => {
+				info!("{addr} resumed player {id}");
+				sim.set_autopilot(&id, false);
+				(id, token)
+			}
+			None => {
+				let max = state.limits.max_players;
+				if max > 0 && sim.human_count() >= max {
+					drop(sim);
+					info!("turning away {addr}: server full ({max} players)");
+					let close = CloseFrame { code: CLOSE_SERVER_FULL, reason: "server full".into() };
+					let _ = sink.send(Message::Close(Some(close))).await;
+					return;
+				}
+				let id = sim.add_player();
+				sim.balance_bots();
+				(id, state.sessions.create(id))
+			}
+		};
+		if let (Some(profiles), Some(profile)) = (&state.profiles, profile.as_mut()) {
+			// A resumed truck keeps playing for the profile it started with
+			if profiles.linked(&id).is_none_or(|linked| linked == profile.id) {
+				profiles.link(id, profile, !is_resumed);
+			}
+		}
+		let welcome = ServerToClient::Welcome {
+			id,
+			world_size: sim.cfg.world_size,
+			map: sim.map.clone(),
+			config: sim.cfg.clone(),
+			session: session.clone(),
+			resumed: is_resumed,
+			spectator: false,
+			profile,
+		};
+		// Send it without holding up the tick loop and every other connection
+		drop(sim);
+		let kicked = state.clients.register(id, addr.ip(), tx_direct.clone());
+		if !send_welcome(&mut sink, &welcome, &state).await {
+			info!("dropping {addr}: couldn't send Welcome");
+			state.clients.unregister(&id, &tx_direct);
+			// A new player never got its session token; a resuming one still has it
+			player_left(state, id, session, is_resumed).await;
+			return;
+		}
+		(id, Some(session), kicked)
 	};
 
-	// Writer task: forwards broadcast state and direct messages to client
-	let writer_handle = tokio::spawn(async move {
+	// Writer task: forwards broadcast state and direct messages to client. Snapshots are full
+	// world states, so a client that falls behind skips straight to the newest one; if it stays
+	// behind for max_behind_secs, or a send blocks for send_timeout_secs, it is disconnected.
+	let limits = state.limits.clone();
+	let metrics = state.metrics.clone();
+	let mut writer_handle = tokio::spawn(async move {
+		let send_timeout = Duration::from_secs_f64(limits.send_timeout_secs);
+		let max_behind = Duration::from_secs_f64(limits.max_behind_secs);
+		let mut behind_since: Option<tokio::time::Instant> = None;
+		let mut skipped_total = 0u64;
+		let mut close_reason = None;
 		loop {
-			tokio::select! {
+			let message = tokio::select! {
 				msg = rx_broadcast.recv() => {
-					match msg {
-						Ok(json) => {
-							if sink.send(Message::Text(json)).await.is_err() {
-								break;
+					let (json, skipped) = match msg {
+						Ok(json) => latest_snapshot(&mut rx_broadcast, json),
+						Err(broadcast::error::RecvError::Lagged(n)) => match rx_broadcast.recv().await {
+							Ok(json) => {
+								let (json, skipped) = latest_snapshot(&mut rx_broadcast, json);
+								(json, skipped + n)
 							}
+							Err(_) => break,
+						},
+						Err(broadcast::error::RecvError::Closed) => break,
+					};
+					if skipped > 0 {
+						skipped_total += skipped;
+						metrics.snapshots_skipped.fetch_add(skipped, Relaxed);
+						let since = *behind_since.get_or_insert_with(tokio::time::Instant::now);
+						if since.elapsed() > max_behind {
+							close_reason = Some("too far behind");
+							break;
 						}
-						Err(_) => break,
+					} else {
+						behind_since = None;
 					}
+					Message::Text(json)
 				}
 				opt = rx_direct.recv() => {
 					match opt {
-						Some(message) => {
-							if sink.send(message).await.is_err() {
-								break;
-							}
-						}
+						Some(message) => message,
 						None => break,
 					}
 				}
+			};
+			let closing = matches!(message, Message::Close(_));
+			match tokio::time::timeout(send_timeout, sink.send(message)).await {
+				Ok(Ok(())) if !closing => {
+					metrics.messages_out.fetch_add(1, Relaxed);
+				}
+				Ok(_) => break,
+				Err(_) => {
+					// Don't try to send a close frame down a socket that isn't draining
+					warn!("disconnecting {addr}: send blocked for {send_timeout:?}");
+					metrics.lag_disconnects.fetch_add(1, Relaxed);
+					return;
+				}
 			}
 		}
+		if skipped_total > 0 {
+			info!("{addr} skipped {skipped_total} stale state snapshots");
+		}
+		if let Some(reason) = close_reason {
+			warn!("disconnecting {addr}: {reason}");
+			metrics.lag_disconnects.fetch_add(1, Relaxed);
+			let close = Message::Close(Some(CloseFrame { code: close_code::AGAIN, reason: reason.into() }));
+			let _ = tokio::time::timeout(send_timeout, sink.send(close)).await;
+		}
 	});
 
-	// Reader: process client messages
-	while let Some(Ok(msg)) = stream.next().await {
-		match msg {
-			Message::Text(txt) => {
-				match serde_json::from_str::<ClientToServer>(&txt) {
-					Ok(ClientToServer::Input { turn, boost }) => {
-						let mut sim = state.sim.lock().await;
-						sim.submit_input(player_id, turn);
-						sim.submit_boost(player_id, boost);
+	// Reader: process client messages. Malformed, binary and over-rate messages count as
+	// protocol errors against a budget that refills over the error window; a client that
+	// runs out of it is disconnected.
+	let limits = &state.limits;
+	let mut rate = RateLimiter::new(limits.messages_per_sec, limits.message_burst);
+	let max_errors = limits.max_protocol_errors as f64;
+	let mut error_budget = RateLimiter::new(max_errors / limits.protocol_error_window_secs, max_errors);
+	let mut chat = ChatThrottle::new(&state.chat);
+	let mut said_hello = false;
+	let mut warned = false;
+	let mut close_reason = None;
+	let mut writer_done = false;
+	let mut clean_close = false;
+	let mut shutdown = state.shutdown.clone();
+	loop {
+		let msg = tokio::select! {
+			msg = stream.next() => msg,
+			_ = &mut writer_handle => {
+				// Writer gave up on this client (slow or gone)
+				writer_done = true;
+				break;
+			}
+			changed = shutdown.changed() => {
+				let phase = if changed.is_ok() { *shutdown.borrow_and_update() } else { ShutdownPhase::Closing };
+				match phase {
+					ShutdownPhase::Running => {}
+					ShutdownPhase::Draining { seconds } => {
+						let msg = ServerToClient::ServerShutdown { reason: SHUTDOWN_REASON.to_string(), seconds };
+						let _ = tx_direct.try_send(Message::Text(serde_json::to_string(&msg).unwrap()));
 					}
-					Ok(ClientToServer::Ping(n)) => {
-						let _ = tx_direct.send(Message::Text(serde_json::to_string(&ServerToClient::Pong(n)).unwrap())).await;
+					ShutdownPhase::Closing => {
+						close_reason = Some((close_code::AWAY, SHUTDOWN_REASON.to_string()));
+						break;
 					}
-					Ok(ClientToServer::Hello { .. }) => {}
-					Err(e) => {
-						error!("bad client msg: {e}");
+				}
+				continue;
+			}
+			Some(reason) = kicked.recv() => {
+				close_reason = Some((CLOSE_KICKED, reason));
+				break;
+			}
+			Ok(()) = gameplay.changed() => {
+				let msg = ServerToClient::ConfigChanged(gameplay.borrow_and_update().clone());
+				let _ = tx_direct.try_send(Message::Text(serde_json::to_string(&msg).unwrap()));
+				continue;
+			}
+		};
+		let Some(msg) = msg else {
+			break;
+		};
+		let msg = match msg {
+			Ok(msg) => msg,
+			Err(e) => {
+				// Abrupt disconnects, and messages over max_message_bytes
+				info!("connection from {addr} closed: {e}");
+				break;
+			}
+		};
+		if matches!(msg, Message::Text(_) | Message::Binary(_)) {
+			state.metrics.messages_in.fetch_add(1, Relaxed);
+		}
+		let error = match msg {
+			Message::Text(_) | Message::Binary(_) if !rate.allow() => Some("rate limit exceeded".to_string()),
+			Message::Text(txt) => match serde_json::from_str::<ClientToServer>(&txt) {
+				// Spectators have no truck to steer
+				Ok(ClientToServer::Input { .. }) if spectator => None,
+				Ok(ClientToServer::Input { turn, boost, steer }) => {
+					let steer = steer.unwrap_or(turn.steer());
+					state.inputs.lock().unwrap().insert(player_id, (steer, boost));
+					None
+				}
+				Ok(ClientToServer::Ping(n)) => {
+					let _ = tx_direct.try_send(Message::Text(serde_json::to_string(&ServerToClient::Pong(n)).unwrap()));
+					None
+				}
+				// Saved to the player's profile, once per connection
+				Ok(ClientToServer::Hello { name, color }) => {
+					let linked = state.profiles.as_ref().and_then(|profiles| Some((profiles.clone(), profiles.linked(&player_id)?)));
+					if let Some((profiles, profile)) = linked.filter(|_| !said_hello) {
+						let name = state.chat.clean(&name).map(|name| {
+							name.chars().take(MAX_NAME_CHARS).collect::<String>().trim_end().to_string()
+						});
+						let color = color.as_deref().and_then(profiles::parse_color);
+						tokio::spawn(async move { profiles.set_details(profile, name, color).await });
 					}
+					said_hello = true;
+					None
 				}
+				Ok(ClientToServer::Chat(text)) => {
+					if let Some(text) = state.chat.clean(&text) {
+						let outgoing = chat.check_message(&text).map(|()| ServerToClient::Chat { from: player_id, text });
+						send_chat(&state, &tx_direct, outgoing);
+					}
+					None
+				}
+				Ok(ClientToServer::Emote(_)) if spectator => {
+					send_chat(&state, &tx_direct, Err("spectators can't use emotes"));
+					None
+				}
+				Ok(ClientToServer::Emote(emote)) => {
+					let outgoing = chat.check_emote().map(|()| ServerToClient::Emote { from: player_id, emote });
+					send_chat(&state, &tx_direct, outgoing);
+					None
+				}
+				Err(e) => Some(format!("bad client msg: {e}")),
+			},
+			Message::Binary(_) => Some("unexpected binary message".to_string()),
+			Message::Close(_) => {
+				clean_close = true;
+				break;
+			}
+			_ => None,
+		};
+		if let Some(error) = error {
+			if !warned {
+				warn!("protocol error from {addr}: {error}");
+				warned = true;
+			}
+			if !error_budget.allow() {
+				close_reason = Some((close_code::POLICY, "too many protocol errors".to_string()));
+				break;
 			}
-			Message::Close(_) => break,
-			Message::Binary(_) => {}
-			_ => {}
 		}
 	}
+	if let Some((code, reason)) = &close_reason {
+		info!("disconnecting {addr}: {reason}");
+		let _ = tx_direct.try_send(Message::Close(Some(CloseFrame { code: *code, reason: reason.clone().into() })));
+	}
 
 	// Cleanup
 	// Drop direct tx to stop writer, then wait for it to end
+	state.clients.unregister(&player_id, &tx_direct);
 	drop(tx_direct);
-	let _ = writer_handle.await;
+	if !writer_done {
+		let _ = writer_handle.await;
+	}
+	let Some(session) = session else {
+		info!("spectator {player_id} left");
+		return;
+	};
+	state.inputs.lock().unwrap().remove(&player_id);
+
+	// A client that closed the connection itself, was kicked (by an admin or for protocol
+	// errors) or is being shut down is gone
+	player_left(state, player_id, session, !clean_close && close_reason.is_none()).await;
+}
+
+// The player's connection has ended. If it `may_return`, keep its truck on autopilot and give
+// it the grace period to reconnect; otherwise remove the truck now.
+async fn player_left(state: AppState, player_id: PlayerId, session: String, may_return: bool) {
+	let generation = state.sessions.disconnect(&session);
+	match generation {
+		Some(generation) if may_return && !state.sessions.grace.is_zero() => {
+			state.sim.lock().await.set_autopilot(&player_id, true);
+			tokio::spawn(async move {
+				tokio::time::sleep(state.sessions.grace).await;
+				if let Some(id) = state.sessions.expire(&session, generation) {
+					info!("session for player {id} expired");
+					remove_player(&state, &id).await;
+				}
+			});
+		}
+		_ => {
+			state.sessions.end(&session);
+			remove_player(&state, &player_id).await;
+		}
+	}
+}
+
+// Send the Welcome, giving up if the client doesn't take it within send_timeout_secs
+async fn send_welcome(sink: &mut SplitSink<WebSocket, Message>, welcome: &ServerToClient, state: &AppState) -> bool {
+	let text = Message::Text(serde_json::to_string(welcome).unwrap());
+	let timeout = Duration::from_secs_f64(state.limits.send_timeout_secs);
+	let sent = matches!(tokio::time::timeout(timeout, sink.send(text)).await, Ok(Ok(())));
+	if sent {
+		state.metrics.messages_out.fetch_add(1, Relaxed);
+	}
+	sent
+}
+
+// Send an allowed chat message or emote to the room, or tell the sender why it was refused
+fn send_chat(state: &AppState, tx_direct: &mpsc::Sender<Message>, outgoing: Result<ServerToClient, &str>) {
+	match outgoing {
+		Ok(msg) => {
+			state.clients.send_all(&msg);
+		}
+		Err(reason) => {
+			let msg = ServerToClient::ChatRejected(reason.to_string());
+			let _ = tx_direct.try_send(Message::Text(serde_json::to_string(&msg).unwrap()));
+		}
+	}
+}
+
+async fn remove_player(state: &AppState, id: &PlayerId) {
 	let mut sim = state.sim.lock().await;
-	sim.remove_player(&player_id);
-	
-	// Spawn a new bot when a player disconnects (to maintain some bots)
-	if sim.bots.len() < 3 {
-		sim.add_bot();
+	if let Some(profiles) = &state.profiles {
+		let trailer_len = sim.state.players.get(id).map_or(0, |p| p.trailer.len());
+		profiles.unlink(id, trailer_len);
 	}
+	sim.remove_player(id);
+	// Let a bot take the free slot
+	sim.balance_bots();
 }
 
+// Skip past any snapshots queued behind `json` and return the newest with the number skipped
+fn latest_snapshot(rx: &mut broadcast::Receiver<String>, mut json: String) -> (String, u64) {
+	let mut skipped = 0;
+	loop {
+		match rx.try_recv() {
+			Ok(newer) => {
+				json = newer;
+				skipped += 1;
+			}
+			Err(broadcast::error::TryRecvError::Lagged(n)) => skipped += n,
+			Err(_) => return (json, skipped),
+		}
+	}
+}
@@ -0,0 +1,164 @@
+// Health checks and Prometheus metrics. Everything here is read from atomics updated by the tick
+// loop and the connections, never from the sim, so the endpoints still answer (and report the
+// problem) when the tick loop is stuck holding the sim lock.
+
+use std::{
+	fmt::Write,
+	sync::atomic::{AtomicU64, Ordering::Relaxed},
+	time::{Duration, Instant},
+};
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, routing::get, Router};
+
+use crate::{AppState, ShutdownPhase};
+
+// With no tick for this long the tick loop counts as stuck and the server as unhealthy
+const STALL_AFTER: Duration = Duration::from_secs(5);
+
+// Upper bounds of the tick duration histogram buckets, in seconds
+const TICK_BUCKETS: [f64; 10] = [0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 1.0];
+
+pub fn router() -> Router<AppState> {
+	Router::new()
+		.route("/healthz", get(healthz))
+		.route("/readyz", get(readyz))
+		.route("/metrics", get(metrics))
+}
+
+pub struct Metrics {
+	started: Instant,
+	last_tick_micros: AtomicU64, // Since `started`; 0 until the first tick
+	pub ticks: AtomicU64,
+	pub tick_overruns: AtomicU64, // Steps that took longer than one tick
+	pub ticks_skipped: AtomicU64, // Dropped when the loop fell too far behind
+	tick_buckets: [AtomicU64; TICK_BUCKETS.len()],
+	tick_micros_total: AtomicU64,
+	pub players: AtomicU64, // Trucks in the game, bots included
+	pub bots: AtomicU64,
+	pub spectators: AtomicU64,
+	pub snapshot_bytes: AtomicU64, // Size of the latest state broadcast
+	pub snapshot_bytes_total: AtomicU64,
+	pub messages_in: AtomicU64,
+	pub messages_out: AtomicU64,
+	pub snapshots_skipped: AtomicU64, // Snapshots lagging clients skipped past
+	pub lag_disconnects: AtomicU64, // Clients disconnected for staying behind or blocking sends
+}
+
+impl Metrics {
+	pub fn new() -> Self {
+		Self {
+			started: Instant::now(),
+			last_tick_micros: AtomicU64::new(0),
+			ticks: AtomicU64::new(0),
+			tick_overruns: AtomicU64::new(0),
+			ticks_skipped: AtomicU64::new(0),
+			tick_buckets: Default::default(),
+			tick_micros_total: AtomicU64::new(0),
+			players: AtomicU64::new(0),
+			bots: AtomicU64::new(0),
+			spectators: AtomicU64::new(0),
+			snapshot_bytes: AtomicU64::new(0),
+			snapshot_bytes_total: AtomicU64::new(0),
+			messages_in: AtomicU64::new(0),
+			messages_out: AtomicU64::new(0),
+			snapshots_skipped: AtomicU64::new(0),
+			lag_disconnects: AtomicU64::new(0),
+		}
+	}
+
+	// Record one sim step that took `took` against a tick budget of `tick`
+	pub fn record_tick(&self, took: Duration, tick: Duration) {
+		self.ticks.fetch_add(1, Relaxed);
+		if took > tick {
+			self.tick_overruns.fetch_add(1, Relaxed);
+		}
+		let secs = took.as_secs_f64();
+		if let Some(i) = TICK_BUCKETS.iter().position(|&le| secs <= le) {
+			self.tick_buckets[i].fetch_add(1, Relaxed);
+		}
+		self.tick_micros_total.fetch_add(took.as_micros() as u64, Relaxed);
+		self.last_tick_micros.store(self.started.elapsed().as_micros().max(1) as u64, Relaxed);
+	}
+
+	pub fn record_snapshot(&self, bytes: usize) {
+		self.snapshot_bytes.store(bytes as u64, Relaxed);
+		self.snapshot_bytes_total.fetch_add(bytes as u64, Relaxed);
+	}
+
+	// Time since the last tick, or None before the first one
+	fn since_last_tick(&self) -> Option<Duration> {
+		match self.last_tick_micros.load(Relaxed) {
+			0 => None,
+			micros => Some(self.started.elapsed().saturating_sub(Duration::from_micros(micros))),
+		}
+	}
+
+	fn ticking(&self) -> bool {
+		self.since_last_tick().is_some_and(|d| d < STALL_AFTER)
+	}
+
+	// Prometheus text exposition format
+	fn render(&self, connections: usize, shutting_down: bool) -> String {
+		let mut out = String::new();
+		let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+			let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}");
+		};
+		metric("hovertruck_up", "gauge", "1 while the tick loop is running", (self.ticking() as u8).to_string());
+		metric("hovertruck_shutting_down", "gauge", "1 once a graceful shutdown has started", (shutting_down as u8).to_string());
+		metric("hovertruck_connected_clients", "gauge", "Open WebSocket connections", connections.to_string());
+		metric("hovertruck_players", "gauge", "Trucks in the game, bots included", self.players.load(Relaxed).to_string());
+		metric("hovertruck_bots", "gauge", "Bot trucks in the game", self.bots.load(Relaxed).to_string());
+		metric("hovertruck_spectators", "gauge", "Connections watching without a truck", self.spectators.load(Relaxed).to_string());
+		metric("hovertruck_ticks_total", "counter", "Simulation steps run", self.ticks.load(Relaxed).to_string());
+		metric("hovertruck_tick_overruns_total", "counter", "Steps that took longer than one tick", self.tick_overruns.load(Relaxed).to_string());
+		metric("hovertruck_ticks_skipped_total", "counter", "Ticks dropped because the loop fell behind", self.ticks_skipped.load(Relaxed).to_string());
+		let since = self.since_last_tick().map_or(f64::NAN, |d| d.as_secs_f64());
+		metric("hovertruck_seconds_since_last_tick", "gauge", "Time since the tick loop last stepped", since.to_string());
+		metric("hovertruck_snapshot_bytes", "gauge", "Size of the latest state broadcast", self.snapshot_bytes.load(Relaxed).to_string());
+		metric("hovertruck_snapshot_bytes_total", "counter", "Bytes of state broadcast, before fan-out to clients", self.snapshot_bytes_total.load(Relaxed).to_string());
+		metric("hovertruck_messages_in_total", "counter", "WebSocket messages received from clients", self.messages_in.load(Relaxed).to_string());
+		metric("hovertruck_messages_out_total", "counter", "WebSocket messages sent to clients", self.messages_out.load(Relaxed).to_string());
+		metric("hovertruck_snapshots_skipped_total", "counter", "State snapshots lagging clients skipped past", self.snapshots_skipped.load(Relaxed).to_string());
+		metric("hovertruck_lag_disconnects_total", "counter", "Clients disconnected for falling behind or blocking sends", self.lag_disconnects.load(Relaxed).to_string());
+
+		let name = "hovertruck_tick_duration_seconds";
+		let _ = writeln!(out, "# HELP {name} Time to run one simulation step\n# TYPE {name} histogram");
+		let mut cumulative = 0;
+		for (le, count) in TICK_BUCKETS.iter().zip(&self.tick_buckets) {
+			cumulative += count.load(Relaxed);
+			let _ = writeln!(out, "{name}_bucket{{le=\"{le}\"}} {cumulative}");
+		}
+		let count = self.ticks.load(Relaxed);
+		let _ = writeln!(out, "{name}_bucket{{le=\"+Inf\"}} {count}");
+		let _ = writeln!(out, "{name}_sum {}", self.tick_micros_total.load(Relaxed) as f64 / 1e6);
+		let _ = writeln!(out, "{name}_count {count}");
+		out
+	}
+}
+
+// Alive as long as the tick loop keeps stepping
+async fn healthz(State(state): State<AppState>) -> impl IntoResponse {
+	match state.metrics.since_last_tick() {
+		Some(since) if since >= STALL_AFTER => {
+			(StatusCode::SERVICE_UNAVAILABLE, format!("tick loop stalled for {:.1}s\n", since.as_secs_f64()))
+		}
+		_ => (StatusCode::OK, "ok\n".to_string()),
+	}
+}
+
+// Ready for players: ticking and not shutting down
+async fn readyz(State(state): State<AppState>) -> impl IntoResponse {
+	if *state.shutdown.borrow() != ShutdownPhase::Running {
+		(StatusCode::SERVICE_UNAVAILABLE, "shutting down\n")
+	} else if !state.metrics.ticking() {
+		(StatusCode::SERVICE_UNAVAILABLE, "tick loop not running\n")
+	} else {
+		(StatusCode::OK, "ready\n")
+	}
+}
+
+async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+	let shutting_down = *state.shutdown.borrow() != ShutdownPhase::Running;
+	let body = state.metrics.render(state.connections.total(), shutting_down);
+	([(axum::http::header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
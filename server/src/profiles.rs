@@ -0,0 +1,371 @@
+// Persistent player profiles in a local SQLite file (`profiles.database`). A client signs in by
+// connecting with a token it keeps (`/ws?profile=<token>`); the first time, a profile is made for
+// it, as long as its address hasn't made too many lately (`profiles.new_per_ip_per_hour`). A
+// profile holds the name and colour the player picked (sent in Hello) and lifetime stats,
+// which are gathered from the sim's events as they happen and written every few seconds.
+// Profiles are public by id (GET /profiles, GET /profiles/<id>); tokens never leave the database.
+
+use std::{
+	collections::HashMap,
+	net::IpAddr,
+	path::{Path, PathBuf},
+	sync::{Arc, Mutex},
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+	extract::{Path as UrlPath, Query, State},
+	http::StatusCode,
+	routing::get,
+	Json, Router,
+};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use shared::{GameEvent, GameSim, PlayerId, Profile, ProfileStats};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{limits::RateLimiter, AppState};
+
+// How often collected stats are written to the database
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+const MAX_LIST: usize = 100;
+
+const SCHEMA: &str = "
+	CREATE TABLE IF NOT EXISTS profiles (
+		id TEXT PRIMARY KEY,
+		token TEXT NOT NULL UNIQUE,
+		name TEXT NOT NULL DEFAULT '',
+		color TEXT,
+		games INTEGER NOT NULL DEFAULT 0,
+		kills INTEGER NOT NULL DEFAULT 0,
+		deaths INTEGER NOT NULL DEFAULT 0,
+		longest_trailer INTEGER NOT NULL DEFAULT 0,
+		created_at INTEGER NOT NULL,
+		last_seen INTEGER NOT NULL
+	);
+";
+const COLUMNS: &str = "id, name, color, games, kills, deaths, longest_trailer";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProfilesConfig {
+	pub database: Option<PathBuf>, // SQLite file, created if missing; profiles are off if unset
+	pub new_per_ip_per_hour: u32, // New profiles one address may make; more play without one (0 = unlimited)
+}
+
+impl Default for ProfilesConfig {
+	fn default() -> Self {
+		Self { database: None, new_per_ip_per_hour: 10 }
+	}
+}
+
+pub struct Profiles {
+	db: Arc<Mutex<Connection>>,
+	players: Mutex<HashMap<PlayerId, Uuid>>, // The profile each truck plays for
+	pending: Mutex<HashMap<Uuid, ProfileStats>>, // Not written yet; counts to add, longest_trailer to raise to
+	new_per_ip_per_hour: u32,
+	created: Mutex<HashMap<IpAddr, RateLimiter>>, // Profiles made lately, per address
+}
+
+impl Profiles {
+	pub fn open(path: &Path, new_per_ip_per_hour: u32) -> anyhow::Result<Self> {
+		let db = Connection::open(path)?;
+		db.execute_batch(SCHEMA)?;
+		let count: u64 = db.query_row("SELECT COUNT(*) FROM profiles", [], |row| row.get(0))?;
+		info!("player profiles in {} ({count} profiles)", path.display());
+		Ok(Self {
+			db: Arc::new(Mutex::new(db)),
+			players: Mutex::default(),
+			pending: Mutex::default(),
+			new_per_ip_per_hour,
+			created: Mutex::default(),
+		})
+	}
+
+	async fn with_db<T: Send + 'static>(
+		&self,
+		f: impl FnOnce(&mut Connection) -> rusqlite::Result<T> + Send + 'static,
+	) -> anyhow::Result<T> {
+		let db = self.db.clone();
+		Ok(tokio::task::spawn_blocking(move || f(&mut db.lock().unwrap())).await??)
+	}
+
+	// The profile for `token`, made now if it is new and `ip` may still make one. None (and the
+	// player plays without one) if the token isn't acceptable, the address has made too many
+	// profiles lately or the database fails.
+	pub async fn sign_in(&self, token: String, ip: IpAddr) -> Option<Profile> {
+		let valid = (16..=64).contains(&token.len())
+			&& token.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+		if !valid {
+			warn!("ignoring malformed profile token");
+			return None;
+		}
+		let existing = {
+			let token = token.clone();
+			self.with_db(move |db| {
+				db.execute("UPDATE profiles SET last_seen = ?2 WHERE token = ?1", params![token, unix_now()])?;
+				db.query_row(&format!("SELECT {COLUMNS} FROM profiles WHERE token = ?1"), [token], profile_from_row)
+					.optional()
+			})
+			.await
+		};
+		match existing {
+			Ok(Some(profile)) => return Some(profile),
+			Ok(None) => {}
+			Err(e) => {
+				warn!("profile sign-in failed: {e:#}");
+				return None;
+			}
+		}
+		if !self.may_create(ip) {
+			warn!("not making a profile for {ip}: too many new profiles from this address");
+			return None;
+		}
+		let result = self
+			.with_db(move |db| {
+				db.execute(
+					"INSERT INTO profiles (id, token, created_at, last_seen) VALUES (?1, ?2, ?3, ?3)
+					ON CONFLICT(token) DO UPDATE SET last_seen = excluded.last_seen",
+					params![Uuid::new_v4().to_string(), token, unix_now()],
+				)?;
+				db.query_row(&format!("SELECT {COLUMNS} FROM profiles WHERE token = ?1"), [token], profile_from_row)
+			})
+			.await;
+		result.inspect_err(|e| warn!("profile sign-in failed: {e:#}")).ok()
+	}
+
+	// Count a new profile against `ip`'s hourly allowance; false if it has none left
+	fn may_create(&self, ip: IpAddr) -> bool {
+		if self.new_per_ip_per_hour == 0 {
+			return true;
+		}
+		let mut created = self.created.lock().unwrap();
+		// Addresses that could make a full hour's worth again are as good as new
+		created.retain(|_, rate| !rate.is_full());
+		let per_hour = self.new_per_ip_per_hour as f64;
+		created.entry(ip).or_insert_with(|| RateLimiter::new(per_hour / 3600.0, per_hour)).allow()
+	}
+
+	// `player` now plays for `profile`; `new_game` if it is a new truck rather than a resumed
+	// one. Adds the stats not written yet to `profile`.
+	pub fn link(&self, player: PlayerId, profile: &mut Profile, new_game: bool) {
+		self.players.lock().unwrap().insert(player, profile.id);
+		let mut pending = self.pending.lock().unwrap();
+		let unsaved = pending.entry(profile.id).or_default();
+		if new_game {
+			unsaved.games += 1;
+		}
+		let stats = &mut profile.stats;
+		stats.games += unsaved.games;
+		stats.kills += unsaved.kills;
+		stats.deaths += unsaved.deaths;
+		stats.longest_trailer = stats.longest_trailer.max(unsaved.longest_trailer);
+	}
+
+	pub fn linked(&self, player: &PlayerId) -> Option<Uuid> {
+		self.players.lock().unwrap().get(player).copied()
+	}
+
+	// The player's truck left the game with a trailer of `trailer_len`
+	pub fn unlink(&self, player: &PlayerId, trailer_len: usize) {
+		if let Some(profile) = self.players.lock().unwrap().remove(player) {
+			let mut pending = self.pending.lock().unwrap();
+			let stats = pending.entry(profile).or_default();
+			stats.longest_trailer = stats.longest_trailer.max(trailer_len as u64);
+		}
+	}
+
+	// Collect stats from the events of the sim's last step
+	pub fn observe(&self, sim: &GameSim) {
+		let players = self.players.lock().unwrap();
+		if players.is_empty() {
+			return;
+		}
+		let mut pending = self.pending.lock().unwrap();
+		for event in &sim.events {
+			match *event {
+				GameEvent::ItemPickup { player } => {
+					let (Some(profile), Some(state)) = (players.get(&player), sim.state.players.get(&player)) else {
+						continue;
+					};
+					let stats = pending.entry(*profile).or_default();
+					stats.longest_trailer = stats.longest_trailer.max(state.trailer.len() as u64);
+				}
+				GameEvent::Death { player, killer, trailer_len, .. } => {
+					if let Some(profile) = players.get(&player) {
+						let stats = pending.entry(*profile).or_default();
+						stats.deaths += 1;
+						stats.longest_trailer = stats.longest_trailer.max(trailer_len as u64);
+					}
+					if let Some(profile) = killer.and_then(|killer| players.get(&killer)) {
+						pending.entry(*profile).or_default().kills += 1;
+					}
+				}
+			}
+		}
+	}
+
+	// Write the collected stats; they are kept for the next try if that fails
+	pub async fn flush(&self) {
+		let batch = std::mem::take(&mut *self.pending.lock().unwrap());
+		if batch.is_empty() {
+			return;
+		}
+		let written = batch.clone();
+		let result = self
+			.with_db(move |db| {
+				let tx = db.transaction()?;
+				for (id, stats) in &written {
+					tx.execute(
+						"UPDATE profiles SET games = games + ?2, kills = kills + ?3, deaths = deaths + ?4,
+						longest_trailer = MAX(longest_trailer, ?5), last_seen = ?6 WHERE id = ?1",
+						params![
+							id.to_string(),
+							stats.games,
+							stats.kills,
+							stats.deaths,
+							stats.longest_trailer,
+							unix_now()
+						],
+					)?;
+				}
+				tx.commit()
+			})
+			.await;
+		if let Err(e) = result {
+			warn!("failed to save profile stats, will retry: {e:#}");
+			let mut pending = self.pending.lock().unwrap();
+			for (id, stats) in batch {
+				let merged = pending.entry(id).or_default();
+				merged.games += stats.games;
+				merged.kills += stats.kills;
+				merged.deaths += stats.deaths;
+				merged.longest_trailer = merged.longest_trailer.max(stats.longest_trailer);
+			}
+		}
+	}
+
+	// Change the profile's name and colour; None keeps the current one
+	pub async fn set_details(&self, profile: Uuid, name: Option<String>, color: Option<String>) {
+		let result = self
+			.with_db(move |db| {
+				db.execute(
+					"UPDATE profiles SET name = COALESCE(?2, name), color = COALESCE(?3, color) WHERE id = ?1",
+					params![profile.to_string(), name, color],
+				)
+			})
+			.await;
+		if let Err(e) = result {
+			warn!("failed to save profile {profile}: {e:#}");
+		}
+	}
+
+	async fn get(&self, id: Uuid) -> anyhow::Result<Option<Profile>> {
+		self.with_db(move |db| {
+			db.query_row(&format!("SELECT {COLUMNS} FROM profiles WHERE id = ?1"), [id.to_string()], profile_from_row)
+				.optional()
+		})
+		.await
+	}
+
+	async fn top(&self, sort: SortBy, limit: usize) -> anyhow::Result<Vec<Profile>> {
+		self.with_db(move |db| {
+			let column = sort.column();
+			let mut query =
+				db.prepare(&format!("SELECT {COLUMNS} FROM profiles ORDER BY {column} DESC, id LIMIT ?1"))?;
+			let rows = query.query_map([limit as i64], profile_from_row)?;
+			rows.collect()
+		})
+		.await
+	}
+}
+
+// Write collected stats every FLUSH_INTERVAL
+pub async fn flush_loop(profiles: Arc<Profiles>) {
+	let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+	loop {
+		interval.tick().await;
+		profiles.flush().await;
+	}
+}
+
+// A `#rrggbb` colour, lower-cased; None if it isn't one
+pub fn parse_color(raw: &str) -> Option<String> {
+	let hex = raw.strip_prefix('#')?;
+	(hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit())).then(|| format!("#{}", hex.to_ascii_lowercase()))
+}
+
+fn profile_from_row(row: &Row) -> rusqlite::Result<Profile> {
+	let id: String = row.get(0)?;
+	Ok(Profile {
+		id: id.parse().map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e)))?,
+		name: row.get(1)?,
+		color: row.get(2)?,
+		stats: ProfileStats { games: row.get(3)?, kills: row.get(4)?, deaths: row.get(5)?, longest_trailer: row.get(6)? },
+	})
+}
+
+fn unix_now() -> i64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64)
+}
+
+pub fn router() -> Router<AppState> {
+	Router::new()
+		.route("/profiles", get(list_profiles))
+		.route("/profiles/:id", get(get_profile))
+}
+
+type ProfileResult<T> = Result<T, (StatusCode, String)>;
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum SortBy {
+	#[default]
+	Kills,
+	Games,
+	LongestTrailer,
+}
+
+impl SortBy {
+	fn column(self) -> &'static str {
+		match self {
+			SortBy::Kills => "kills",
+			SortBy::Games => "games",
+			SortBy::LongestTrailer => "longest_trailer",
+		}
+	}
+}
+
+#[derive(Deserialize)]
+struct ListQuery {
+	#[serde(default)]
+	sort: SortBy,
+	limit: Option<usize>,
+}
+
+fn profiles(state: &AppState) -> ProfileResult<&Arc<Profiles>> {
+	state
+		.profiles
+		.as_ref()
+		.ok_or((StatusCode::NOT_FOUND, "profiles are not enabled on this server".to_string()))
+}
+
+fn db_error(e: anyhow::Error) -> (StatusCode, String) {
+	warn!("profile lookup failed: {e:#}");
+	(StatusCode::INTERNAL_SERVER_ERROR, "profile database error".to_string())
+}
+
+// The best players, by kills unless `?sort=games` or `?sort=longest_trailer`; `?limit=` up to 100
+async fn list_profiles(State(state): State<AppState>, Query(query): Query<ListQuery>) -> ProfileResult<Json<Vec<Profile>>> {
+	let limit = query.limit.unwrap_or(20).min(MAX_LIST);
+	let list = profiles(&state)?.top(query.sort, limit).await.map_err(db_error)?;
+	Ok(Json(list))
+}
+
+async fn get_profile(State(state): State<AppState>, UrlPath(id): UrlPath<Uuid>) -> ProfileResult<Json<Profile>> {
+	match profiles(&state)?.get(id).await.map_err(db_error)? {
+		Some(profile) => Ok(Json(profile)),
+		None => Err((StatusCode::NOT_FOUND, format!("no profile with id {id}"))),
+	}
+}
@@ -0,0 +1,108 @@
+// Hot reload of gameplay tuning. The configuration is loaded again from the same file,
+// environment and flags as at startup. Changed `gameplay` settings are swapped into the sim
+// between ticks and pushed to every client (ConfigChanged) so their prediction keeps matching;
+// changes anywhere else only take effect after a restart and are reported as such.
+
+use std::{path::PathBuf, time::Duration};
+
+use serde::Serialize;
+use shared::{GameConfig, GameSim};
+use tracing::{info, warn};
+
+use crate::{config::ServerConfig, AppState};
+
+// How often the config file's modification time is checked
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Default, Serialize)]
+pub struct ReloadOutcome {
+	pub applied: Vec<String>, // "gameplay.player_speed: 12.0 -> 14.0"
+	pub restart_required: Vec<String>, // Changed settings that can't be applied to a running server
+}
+
+pub async fn reload(state: &AppState) -> anyhow::Result<ReloadOutcome> {
+	let new = ServerConfig::load(&state.cli)?;
+	let startup = &state.config;
+	let mut outcome = ReloadOutcome::default();
+	outcome.restart_required.extend(changes("network", &startup.network, &new.network));
+	outcome.restart_required.extend(changes("limits", &startup.limits, &new.limits));
+	outcome.restart_required.extend(changes("room", &startup.room, &new.room));
+	outcome.restart_required.extend(changes("chat", &startup.chat, &new.chat));
+	outcome.restart_required.extend(changes("profiles", &startup.profiles, &new.profiles));
+	// The arena's size is fixed once it is built (and a map sets its own)
+	if new.gameplay.world_size != startup.gameplay.world_size {
+		outcome.restart_required.push(format!(
+			"gameplay.world_size: {} -> {}",
+			startup.gameplay.world_size, new.gameplay.world_size
+		));
+	}
+
+	outcome.applied = apply_gameplay(state, new.gameplay).await;
+	for change in &outcome.applied {
+		info!("config reload: {change}");
+	}
+	for change in &outcome.restart_required {
+		warn!("config reload: {change} needs a restart to take effect");
+	}
+	if outcome.applied.is_empty() && outcome.restart_required.is_empty() {
+		info!("config reload: nothing changed");
+	}
+	Ok(outcome)
+}
+
+// Swap new gameplay tuning into the sim between ticks and pass it on to the clients. The
+// arena keeps its size. Returns the settings that changed.
+pub async fn apply_gameplay(state: &AppState, gameplay: GameConfig) -> Vec<String> {
+	let mut sim = state.sim.lock().await;
+	swap_gameplay(state, &mut sim, gameplay)
+}
+
+// The same, for a caller already holding the sim lock (so it can derive the new tuning from
+// the current one without a reload slipping in between)
+pub fn swap_gameplay(state: &AppState, sim: &mut GameSim, mut gameplay: GameConfig) -> Vec<String> {
+	gameplay.world_size = sim.cfg.world_size;
+	let changed = changes("gameplay", &sim.cfg, &gameplay);
+	if !changed.is_empty() {
+		sim.set_config(gameplay);
+		state.gameplay.send_replace(sim.cfg.clone());
+	}
+	changed
+}
+
+// Reload whenever the config file is modified. A file that fails to load is logged and the
+// current settings are kept.
+pub async fn watch_file(state: AppState, path: PathBuf) {
+	let modified = |path: &PathBuf| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+	let mut last = modified(&path);
+	info!("watching {} for gameplay changes", path.display());
+	let mut interval = tokio::time::interval(WATCH_INTERVAL);
+	loop {
+		interval.tick().await;
+		let current = modified(&path);
+		// Missing while an editor replaces it; wait for the new file
+		if current.is_none() || current == last {
+			continue;
+		}
+		last = current;
+		if let Err(e) = reload(&state).await {
+			warn!("config reload failed, keeping the current settings: {e:#}");
+		}
+	}
+}
+
+// Settings that differ between two versions of a config section, as "section.name: old -> new"
+fn changes<T: Serialize>(section: &str, old: &T, new: &T) -> Vec<String> {
+	let (Ok(old), Ok(new)) = (toml::Table::try_from(old), toml::Table::try_from(new)) else {
+		return Vec::new();
+	};
+	let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+	keys.sort();
+	keys.dedup();
+	keys.into_iter()
+		.filter(|key| old.get(*key) != new.get(*key))
+		.map(|key| {
+			let show = |v: Option<&toml::Value>| v.map_or("unset".to_string(), |v| v.to_string());
+			format!("{section}.{key}: {} -> {}", show(old.get(key)), show(new.get(key)))
+		})
+		.collect()
+}
@@ -0,0 +1,76 @@
+// Resumable sessions: every connection gets a token in Welcome. When the socket drops, the
+// truck stays in the game on autopilot for a grace period; reconnecting with the token within
+// that time takes it back, otherwise the player is removed.
+
+use std::{
+	collections::HashMap,
+	sync::{Arc, Mutex},
+	time::Duration,
+};
+
+use shared::PlayerId;
+use uuid::Uuid;
+
+struct Session {
+	player_id: PlayerId,
+	connected: bool,
+	generation: u64, // Bumped on every (re)connect so a stale expiry can't remove a resumed player
+}
+
+#[derive(Clone)]
+pub struct Sessions {
+	sessions: Arc<Mutex<HashMap<String, Session>>>,
+	pub grace: Duration,
+}
+
+impl Sessions {
+	pub fn new(grace: Duration) -> Self {
+		Self { sessions: Arc::default(), grace }
+	}
+
+	// Start a session for a newly added player and return its token
+	pub fn create(&self, player_id: PlayerId) -> String {
+		let token = Uuid::new_v4().simple().to_string();
+		let session = Session { player_id, connected: true, generation: 0 };
+		self.sessions.lock().unwrap().insert(token.clone(), session);
+		token
+	}
+
+	// Take back a disconnected session's player. None for unknown or expired tokens, and for
+	// sessions that are still connected elsewhere.
+	pub fn resume(&self, token: &str) -> Option<PlayerId> {
+		let mut sessions = self.sessions.lock().unwrap();
+		let session = sessions.get_mut(token).filter(|s| !s.connected)?;
+		session.connected = true;
+		session.generation += 1;
+		Some(session.player_id)
+	}
+
+	// Mark the session disconnected; returns the generation to pass to `expire`
+	pub fn disconnect(&self, token: &str) -> Option<u64> {
+		let mut sessions = self.sessions.lock().unwrap();
+		let session = sessions.get_mut(token)?;
+		session.connected = false;
+		Some(session.generation)
+	}
+
+	// End the session if nobody resumed it since `disconnect` returned `generation`.
+	// Returns the player to remove.
+	pub fn expire(&self, token: &str, generation: u64) -> Option<PlayerId> {
+		let mut sessions = self.sessions.lock().unwrap();
+		match sessions.get(token) {
+			Some(s) if !s.connected && s.generation == generation => sessions.remove(token).map(|s| s.player_id),
+			_ => None,
+		}
+	}
+
+	// Drop the session without a grace period
+	pub fn end(&self, token: &str) {
+		self.sessions.lock().unwrap().remove(token);
+	}
+
+	// Drop whatever session `player_id` has, e.g. when an admin removes the player
+	pub fn end_player(&self, player_id: &PlayerId) {
+		self.sessions.lock().unwrap().retain(|_, s| s.player_id != *player_id);
+	}
+}
@@ -0,0 +1,495 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+use crate::physics::{turn_rate, TRUCK_RADIUS};
+use crate::{GameConfig, GameSim, MapDef, Obb, ObstacleShape, PlayerId, PlayerState, TurnInput, Vec3, WorldState};
+
+// How far ahead (in seconds) bots simulate each turn option when looking for danger
+const LOOKAHEAD_SECS: f32 = 1.2;
+const LOOKAHEAD_STEP: f32 = 1.0 / 15.0;
+
+// Drives one bot. Implementations get a read-only view of the world each tick and return
+// the turn and boost input to submit for their truck.
+pub trait BotController: Send + Sync {
+	fn name(&self) -> &'static str;
+	fn decide(&mut self, obs: &Observation) -> (TurnInput, bool);
+}
+
+// What a bot can see: its own truck plus the shared world, map and tuning
+pub struct Observation<'a> {
+	pub id: PlayerId,
+	pub me: &'a PlayerState,
+	pub world: &'a WorldState,
+	pub map: &'a MapDef,
+	pub cfg: &'a GameConfig,
+	pub aggression: f32, // 0..1 from the bot's difficulty; how much risk a controller should take
+}
+
+impl<'a> Observation<'a> {
+	// None if the player doesn't exist or is dead
+	pub fn new(sim: &'a GameSim, id: PlayerId) -> Option<Self> {
+		let me = sim.state.players.get(&id)?;
+		if !me.alive {
+			return None;
+		}
+		let aggression = sim.bot_brains.get(&id).map_or(BotDifficulty::default().aggression, |b| b.difficulty.aggression);
+		Some(Self { id, me, world: &sim.state, map: &sim.map, cfg: &sim.cfg, aggression })
+	}
+
+	// Living trucks other than this bot
+	pub fn others(&self) -> impl Iterator<Item = &'a PlayerState> + '_ {
+		self.world.players.values().filter(move |p| p.id != self.id && p.alive)
+	}
+
+	pub fn forward(&self) -> (f32, f32) {
+		(self.me.rotation_y.sin(), self.me.rotation_y.cos())
+	}
+
+	pub fn distance_to(&self, pos: Vec3) -> f32 {
+		let dx = pos.x - self.me.position.x;
+		let dz = pos.z - self.me.position.z;
+		(dx * dx + dz * dz).sqrt()
+	}
+
+	// Distance along a unit ray to the first thing that would kill this truck (arena edge or
+	// zone, solid obstacles, other trucks and their trailers), capped at `max_dist`.
+	pub fn raycast(&self, origin: Vec3, dx: f32, dz: f32, max_dist: f32) -> f32 {
+		let mut best = max_dist;
+
+		// Arena boundary / shrinking zone, hit from the inside
+		let half = self.world.zone_half_size.min(self.cfg.world_size) - TRUCK_RADIUS;
+		for (o, d) in [(origin.x, dx), (origin.z, dz)] {
+			if d > 1e-6 {
+				best = best.min(((half - o) / d).max(0.0));
+			} else if d < -1e-6 {
+				best = best.min(((-half - o) / d).max(0.0));
+			}
+		}
+
+		for obstacle in &self.map.obstacles {
+			let hit = match obstacle.shape {
+				ObstacleShape::Pillar { radius, .. } => {
+					ray_circle(origin.x, origin.z, dx, dz, obstacle.x, obstacle.z, radius + TRUCK_RADIUS)
+				}
+				ObstacleShape::Wall { width, length, .. } => {
+					let (lx, lz) = obstacle.to_local(origin.x, origin.z);
+					let (sin, cos) = obstacle.rotation_y.sin_cos();
+					let ldx = dx * cos - dz * sin;
+					let ldz = dx * sin + dz * cos;
+					ray_box(lx, lz, ldx, ldz, width / 2.0 + TRUCK_RADIUS, length / 2.0 + TRUCK_RADIUS)
+				}
+				ObstacleShape::Ramp { .. } => None,
+			};
+			if let Some(t) = hit {
+				best = best.min(t);
+			}
+		}
+
+		// Other trucks and carts as their collision boxes, grown by our own truck radius
+		for other in self.others() {
+			let boxes = std::iter::once(Obb::truck(other, self.cfg)).chain(Obb::carts(other, self.cfg));
+			for obb in boxes {
+				let (lx, lz) = obb.to_local(origin.x, origin.z);
+				let (ldx, ldz) = obb.local_dir(dx, dz);
+				if let Some(t) = ray_box(lx, lz, ldx, ldz, obb.half_width + TRUCK_RADIUS, obb.half_length + TRUCK_RADIUS) {
+					best = best.min(t);
+				}
+			}
+		}
+
+		best.clamp(0.0, max_dist)
+	}
+
+	// Clear distance straight ahead of the truck
+	pub fn clearance_ahead(&self, max_dist: f32) -> f32 {
+		let (fx, fz) = self.forward();
+		self.raycast(self.me.position, fx, fz, max_dist)
+	}
+
+	// Simulate holding `turn` for LOOKAHEAD_SECS and return the time until the truck would die
+	// (LOOKAHEAD_SECS if the whole arc is safe)
+	pub fn time_to_collision(&self, turn: TurnInput, boost: bool) -> f32 {
+		let speed = self.cfg.player_speed * if boost { self.cfg.boost_multiplier } else { 1.0 };
+		let mut pos = self.me.position;
+		let mut yaw = self.me.rotation_y;
+		let mut t = 0.0;
+		while t < LOOKAHEAD_SECS {
+			yaw += turn.steer() * turn_rate(self.cfg, boost) * LOOKAHEAD_STEP;
+			let (dx, dz) = (yaw.sin(), yaw.cos());
+			let seg = speed * LOOKAHEAD_STEP;
+			let hit = self.raycast(pos, dx, dz, seg);
+			if hit < seg {
+				return t + LOOKAHEAD_STEP * hit / seg;
+			}
+			pos.x += dx * seg;
+			pos.z += dz * seg;
+			t += LOOKAHEAD_STEP;
+		}
+		LOOKAHEAD_SECS
+	}
+}
+
+// Ray vs circle; returns the distance to the first hit in front of the origin (0 if inside)
+fn ray_circle(ox: f32, oz: f32, dx: f32, dz: f32, cx: f32, cz: f32, r: f32) -> Option<f32> {
+	let mx = ox - cx;
+	let mz = oz - cz;
+	let c = mx * mx + mz * mz - r * r;
+	if c <= 0.0 {
+		return Some(0.0);
+	}
+	let b = mx * dx + mz * dz;
+	if b > 0.0 {
+		return None; // Pointing away
+	}
+	let disc = b * b - c;
+	if disc < 0.0 {
+		return None;
+	}
+	Some(-b - disc.sqrt())
+}
+
+// Ray vs axis-aligned box centred on the origin (slab test)
+fn ray_box(ox: f32, oz: f32, dx: f32, dz: f32, hx: f32, hz: f32) -> Option<f32> {
+	let mut t_min = f32::NEG_INFINITY;
+	let mut t_max = f32::INFINITY;
+	for (o, d, h) in [(ox, dx, hx), (oz, dz, hz)] {
+		if d.abs() < 1e-6 {
+			if o.abs() > h {
+				return None;
+			}
+		} else {
+			let t1 = (-h - o) / d;
+			let t2 = (h - o) / d;
+			t_min = t_min.max(t1.min(t2));
+			t_max = t_max.min(t1.max(t2));
+		}
+	}
+	if t_max < t_min.max(0.0) {
+		return None;
+	}
+	Some(t_min.max(0.0))
+}
+
+// Turn towards a point using the sign of the cross product (turns left if the target is dead behind)
+pub fn steer_towards(player: &PlayerState, target: Vec3) -> TurnInput {
+	let dx = target.x - player.position.x;
+	let dz = target.z - player.position.z;
+	let dist = (dx * dx + dz * dz).sqrt();
+	if dist < 0.1 {
+		return TurnInput::Straight;
+	}
+	let (fx, fz) = (player.rotation_y.sin(), player.rotation_y.cos());
+	let cross = (fx * dz - fz * dx) / dist;
+	let dot = (fx * dx + fz * dz) / dist;
+	let turn_threshold = 0.1; // Turn if more than this far off
+	if cross.abs() > turn_threshold || dot < 0.0 {
+		if cross >= 0.0 { TurnInput::Left } else { TurnInput::Right }
+	} else {
+		TurnInput::Straight
+	}
+}
+
+// Pick the item with the best value-to-risk ratio among the nearest few. Items in clusters are
+// worth more; items behind a hazard, next to trailers or hugging the zone edge are riskier.
+pub fn pick_item(obs: &Observation) -> Option<(Vec3, f32)> {
+	let mut nearest: Vec<(Vec3, f32)> = obs.world.items.values()
+		.map(|item| (item.pos, obs.distance_to(item.pos)))
+		.collect();
+	nearest.sort_by(|a, b| a.1.total_cmp(&b.1));
+	nearest.truncate(8);
+
+	let origin = obs.me.position;
+	let zone = obs.world.zone_half_size.min(obs.cfg.world_size);
+	let mut best: Option<(Vec3, f32, f32)> = None;
+	for &(pos, dist) in &nearest {
+		let value = 1.0 + 0.5 * obs.world.items.values()
+			.filter(|other| {
+				let dx = other.pos.x - pos.x;
+				let dz = other.pos.z - pos.z;
+				let d_sq = dx * dx + dz * dz;
+				d_sq > 0.01 && d_sq < 10.0 * 10.0
+			})
+			.count() as f32;
+
+		let mut risk = 0.0;
+		if dist > 0.1 {
+			let clear = obs.raycast(origin, (pos.x - origin.x) / dist, (pos.z - origin.z) / dist, dist);
+			if clear < dist - 0.5 {
+				risk += 3.0;
+			}
+		}
+		let near_trailer = obs.others()
+			.flat_map(|p| p.trailer.iter())
+			.any(|c| {
+				let dx = c.x - pos.x;
+				let dz = c.z - pos.z;
+				dx * dx + dz * dz < 4.0 * 4.0
+			});
+		if near_trailer {
+			risk += 1.5;
+		}
+		if zone - pos.x.abs() < 4.0 || zone - pos.z.abs() < 4.0 {
+			risk += 1.0;
+		}
+
+		let score = value / (dist.max(1.0) * (1.0 + risk));
+		if best.is_none_or(|(_, _, s)| score > s) {
+			best = Some((pos, dist, score));
+		}
+	}
+	best.map(|(pos, dist, _)| (pos, dist))
+}
+
+// Pick the safest of the turn options, keeping `desired` unless it leads into danger.
+// Returns the chosen turn and whether the truck is in danger at all.
+pub fn avoid_danger(obs: &Observation, desired: TurnInput) -> (TurnInput, bool) {
+	let desired_ttc = obs.time_to_collision(desired, false);
+	if desired_ttc >= LOOKAHEAD_SECS {
+		return (desired, false);
+	}
+	let mut best = (desired, desired_ttc);
+	for option in [TurnInput::Straight, TurnInput::Left, TurnInput::Right] {
+		if option == desired {
+			continue;
+		}
+		let ttc = obs.time_to_collision(option, false);
+		if ttc > best.1 {
+			best = (option, ttc);
+		}
+	}
+	(best.0, true)
+}
+
+// Random drift when there's nothing to go for. Derived from the bot id and tick rather than a
+// thread RNG so seeded sims stay reproducible.
+fn wander(obs: &Observation) -> TurnInput {
+	let mut x = (obs.id.as_u128() as u64) ^ obs.world.tick.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+	x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+	x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+	x ^= x >> 31;
+	match x % 20 {
+		0 => TurnInput::Left, // 10% chance to turn each tick
+		1 => TurnInput::Right,
+		_ => TurnInput::Straight,
+	}
+}
+
+// Go for the best-value item, never take a turn that leads into a wall, obstacle or trailer,
+// and only boost on a clear run to a distant target.
+pub struct Collector;
+
+impl BotController for Collector {
+	fn name(&self) -> &'static str {
+		"collector"
+	}
+
+	fn decide(&mut self, obs: &Observation) -> (TurnInput, bool) {
+		let target = pick_item(obs);
+		let desired = match target {
+			Some((pos, _)) => steer_towards(obs.me, pos),
+			None => wander(obs),
+		};
+		let (turn, danger) = avoid_danger(obs, desired);
+		let boost = !danger
+			&& obs.me.boost_meter > 0.3
+			&& target.is_some_and(|(_, dist)| dist > 20.0 - 10.0 * obs.aggression && obs.clearance_ahead(30.0) >= 30.0);
+		(turn, boost)
+	}
+}
+
+// Chases the nearest truck and aims ahead of it to cut it off with its own trailer; collects
+// items when nobody is in range.
+pub struct Hunter;
+
+impl BotController for Hunter {
+	fn name(&self) -> &'static str {
+		"hunter"
+	}
+
+	fn decide(&mut self, obs: &Observation) -> (TurnInput, bool) {
+		let prey = obs.others()
+			.map(|p| (p, obs.distance_to(p.position)))
+			.filter(|(_, dist)| *dist < 20.0 + 40.0 * obs.aggression)
+			.min_by(|a, b| a.1.total_cmp(&b.1));
+		let Some((prey, dist)) = prey else {
+			return Collector.decide(obs);
+		};
+		// Aim at a point ahead of the prey so we cross its path in front of its nose
+		let lead = (dist * 0.6).clamp(3.0, 15.0);
+		let intercept = Vec3 {
+			x: prey.position.x + prey.rotation_y.sin() * lead,
+			y: prey.position.y,
+			z: prey.position.z + prey.rotation_y.cos() * lead,
+		};
+		let (turn, danger) = avoid_danger(obs, steer_towards(obs.me, intercept));
+		let boost = !danger && obs.me.boost_meter > 0.4 && dist < 25.0 && obs.clearance_ahead(15.0) >= 15.0;
+		(turn, boost)
+	}
+}
+
+// Keeps away from other trucks, boosting to escape when one gets close; only collects items
+// that nobody else is near.
+pub struct Coward;
+
+impl BotController for Coward {
+	fn name(&self) -> &'static str {
+		"coward"
+	}
+
+	fn decide(&mut self, obs: &Observation) -> (TurnInput, bool) {
+		let threat = obs.others()
+			.map(|p| (p.position, obs.distance_to(p.position)))
+			.filter(|(_, dist)| *dist < 24.0 - 12.0 * obs.aggression)
+			.min_by(|a, b| a.1.total_cmp(&b.1));
+		if let Some((threat_pos, dist)) = threat {
+			// Run directly away from the closest truck
+			let me = obs.me.position;
+			let away = Vec3 {
+				x: me.x + (me.x - threat_pos.x) / dist.max(0.1) * 10.0,
+				y: me.y,
+				z: me.z + (me.z - threat_pos.z) / dist.max(0.1) * 10.0,
+			};
+			let (turn, danger) = avoid_danger(obs, steer_towards(obs.me, away));
+			let boost = !danger && dist < 8.0 && obs.me.boost_meter > 0.2;
+			return (turn, boost);
+		}
+
+		let target = pick_item(obs).filter(|(pos, _)| {
+			obs.others().all(|p| {
+				let dx = p.position.x - pos.x;
+				let dz = p.position.z - pos.z;
+				dx * dx + dz * dz > 12.0 * 12.0
+			})
+		});
+		let desired = match target {
+			Some((pos, _)) => steer_towards(obs.me, pos),
+			None => wander(obs),
+		};
+		(avoid_danger(obs, desired).0, false)
+	}
+}
+
+// Built-in bot personalities
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BotPersonality {
+	Collector,
+	Hunter,
+	Coward,
+}
+
+impl BotPersonality {
+	pub const ALL: [BotPersonality; 3] = [BotPersonality::Collector, BotPersonality::Hunter, BotPersonality::Coward];
+
+	pub fn controller(self) -> Box<dyn BotController> {
+		match self {
+			BotPersonality::Collector => Box::new(Collector),
+			BotPersonality::Hunter => Box::new(Hunter),
+			BotPersonality::Coward => Box::new(Coward),
+		}
+	}
+}
+
+impl FromStr for BotPersonality {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_ascii_lowercase().as_str() {
+			"collector" => Ok(BotPersonality::Collector),
+			"hunter" => Ok(BotPersonality::Hunter),
+			"coward" => Ok(BotPersonality::Coward),
+			other => Err(format!("unknown bot personality '{other}' (expected collector, hunter or coward)")),
+		}
+	}
+}
+
+// How well a bot plays, independent of its personality
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BotDifficulty {
+	pub reaction_delay_secs: f32, // Decisions are applied this long after they're made
+	pub turn_accuracy: f32, // Chance (0..1) per tick that the intended turn is used rather than a random one
+	pub aggression: f32, // 0..1, passed to controllers via the observation
+}
+
+impl BotDifficulty {
+	pub const EASY: BotDifficulty = BotDifficulty { reaction_delay_secs: 0.3, turn_accuracy: 0.75, aggression: 0.2 };
+	pub const NORMAL: BotDifficulty = BotDifficulty { reaction_delay_secs: 0.1, turn_accuracy: 0.92, aggression: 0.5 };
+	pub const HARD: BotDifficulty = BotDifficulty { reaction_delay_secs: 0.0, turn_accuracy: 1.0, aggression: 0.9 };
+
+	// Each value within its range; NaN or infinite ones (clamp lets NaN through) fall back to
+	// the default's
+	pub fn clamped(self) -> Self {
+		let default = Self::default();
+		let within = |v: f32, fallback: f32, max: f32| if v.is_finite() { v } else { fallback }.clamp(0.0, max);
+		Self {
+			reaction_delay_secs: within(self.reaction_delay_secs, default.reaction_delay_secs, 2.0),
+			turn_accuracy: within(self.turn_accuracy, default.turn_accuracy, 1.0),
+			aggression: within(self.aggression, default.aggression, 1.0),
+		}
+	}
+}
+
+impl Default for BotDifficulty {
+	fn default() -> Self {
+		Self::NORMAL
+	}
+}
+
+impl FromStr for BotDifficulty {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_ascii_lowercase().as_str() {
+			"easy" => Ok(BotDifficulty::EASY),
+			"normal" => Ok(BotDifficulty::NORMAL),
+			"hard" => Ok(BotDifficulty::HARD),
+			other => Err(format!("unknown bot difficulty '{other}' (expected easy, normal or hard)")),
+		}
+	}
+}
+
+// A controller plus the difficulty it plays at. Applies reaction delay and turn mistakes on
+// top of whatever the controller decides.
+pub struct BotBrain {
+	pub controller: Box<dyn BotController>,
+	pub difficulty: BotDifficulty,
+	pending: VecDeque<(TurnInput, bool)>,
+	rng: StdRng, // Turn mistakes
+}
+
+impl BotBrain {
+	pub fn new(controller: Box<dyn BotController>, difficulty: BotDifficulty) -> Self {
+		Self { controller, difficulty: difficulty.clamped(), pending: VecDeque::new(), rng: StdRng::from_entropy() }
+	}
+
+	pub fn with_seed(controller: Box<dyn BotController>, difficulty: BotDifficulty, seed: u64) -> Self {
+		Self { rng: StdRng::seed_from_u64(seed), ..Self::new(controller, difficulty) }
+	}
+
+	pub fn think(&mut self, obs: &Observation, dt: f32) -> (TurnInput, bool) {
+		let (mut turn, boost) = self.controller.decide(obs);
+		if !self.rng.gen_bool(self.difficulty.turn_accuracy as f64) {
+			turn = [TurnInput::Left, TurnInput::Right, TurnInput::Straight][self.rng.gen_range(0..3)];
+		}
+		self.pending.push_back((turn, boost));
+		let delay_ticks = (self.difficulty.reaction_delay_secs / dt).round() as usize;
+		while self.pending.len() > delay_ticks + 1 {
+			self.pending.pop_front();
+		}
+		self.pending.front().copied().unwrap_or((turn, boost))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn clamped_difficulty_replaces_nan() {
+		let bad = BotDifficulty { reaction_delay_secs: f32::NAN, turn_accuracy: f32::NAN, aggression: f32::INFINITY };
+		assert_eq!(bad.clamped(), BotDifficulty::default());
+		let high = BotDifficulty { reaction_delay_secs: 9.0, turn_accuracy: 1.5, aggression: -1.0 };
+		assert_eq!(high.clamped(), BotDifficulty { reaction_delay_secs: 2.0, turn_accuracy: 1.0, aggression: 0.0 });
+	}
+}
@@ -0,0 +1,181 @@
+// Oriented-rectangle (OBB) collision on the ground plane, matching the rendered truck and cart
+// cuboids. Heights are ignored; everything hovers at the same level.
+
+use crate::{GameConfig, PlayerState, Vec3};
+
+// A rectangle centred at (x, z), rotated by `heading` (yaw, forward = (sin, cos) like trucks)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Obb {
+	pub x: f32,
+	pub z: f32,
+	pub heading: f32,
+	pub half_width: f32,
+	pub half_length: f32, // Along the heading
+}
+
+impl Obb {
+	pub fn new(center: Vec3, heading: f32, width: f32, length: f32) -> Self {
+		Self { x: center.x, z: center.z, heading, half_width: width * 0.5, half_length: length * 0.5 }
+	}
+
+	// Footprint of a truck's head
+	pub fn truck(player: &PlayerState, cfg: &GameConfig) -> Self {
+		Self::new(player.position, player.rotation_y, cfg.truck_width, cfg.truck_length)
+	}
+
+	// Footprints of a truck's carts (the trailer minus the truck itself)
+	pub fn carts<'a>(player: &'a PlayerState, cfg: &'a GameConfig) -> impl Iterator<Item = Obb> + 'a {
+		player.trailer.iter().enumerate().skip(1).map(move |(order, pos)| {
+			let heading = player.trailer_headings.get(order).copied().unwrap_or(player.rotation_y);
+			Self::new(*pos, heading, cfg.cart_width, cfg.cart_length)
+		})
+	}
+
+	// World point in the box's frame: (right, forward) offsets from its centre
+	pub fn to_local(&self, x: f32, z: f32) -> (f32, f32) {
+		self.local_dir(x - self.x, z - self.z)
+	}
+
+	// World direction in the box's frame
+	pub fn local_dir(&self, dx: f32, dz: f32) -> (f32, f32) {
+		let (sin, cos) = self.heading.sin_cos();
+		(dx * cos - dz * sin, dx * sin + dz * cos)
+	}
+
+	fn axes(&self) -> [(f32, f32); 2] {
+		let (sin, cos) = self.heading.sin_cos();
+		[(sin, cos), (cos, -sin)] // Forward, right
+	}
+
+	// Half the extent of the box projected onto unit `axis`
+	fn project_radius(&self, axis: (f32, f32)) -> f32 {
+		let [forward, right] = self.axes();
+		self.half_length * dot(forward, axis).abs() + self.half_width * dot(right, axis).abs()
+	}
+
+	// Separating axis test over both boxes' edge normals. Touching counts as overlapping.
+	pub fn overlaps(&self, other: &Obb) -> bool {
+		let offset = (other.x - self.x, other.z - self.z);
+		self.axes().into_iter().chain(other.axes()).all(|axis| {
+			dot(offset, axis).abs() <= self.project_radius(axis) + other.project_radius(axis)
+		})
+	}
+}
+
+fn dot(a: (f32, f32), b: (f32, f32)) -> f32 {
+	a.0 * b.0 + a.1 * b.1
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, TAU};
+
+	fn obb(x: f32, z: f32, heading: f32, width: f32, length: f32) -> Obb {
+		Obb::new(Vec3 { x, y: 0.0, z }, heading, width, length)
+	}
+
+	#[test]
+	fn identical_boxes_overlap() {
+		let a = obb(3.0, -2.0, 1.0, 0.8, 1.8);
+		assert!(a.overlaps(&a));
+	}
+
+	#[test]
+	fn nose_to_tail_gap_is_separate() {
+		// Trucks in a line along +z, 0.1 apart
+		let a = obb(0.0, 0.0, 0.0, 0.8, 1.8);
+		let b = obb(0.0, 1.9, 0.0, 0.8, 1.8);
+		assert!(!a.overlaps(&b));
+		assert!(!b.overlaps(&a));
+	}
+
+	#[test]
+	fn touching_edges_overlap() {
+		let a = obb(0.0, 0.0, 0.0, 0.8, 1.8);
+		let b = obb(0.0, 1.8, 0.0, 0.8, 1.8);
+		assert!(a.overlaps(&b));
+	}
+
+	#[test]
+	fn side_swipe_along_a_long_cart_hits() {
+		// Truck driving alongside a cart, overlapping only near the cart's rear end. Circle
+		// radii 0.5 + 0.35 between the centres would miss this (distance ~0.98).
+		let cart = obb(0.0, 0.0, 0.0, 0.7, 1.4);
+		let truck = obb(0.7, -0.65, 0.0, 0.8, 1.8);
+		assert!(cart.overlaps(&truck));
+	}
+
+	#[test]
+	fn heading_matters_for_long_boxes() {
+		// Same centres: facing each other along x they overlap, parallel along z they don't
+		let a = obb(0.0, 0.0, FRAC_PI_2, 0.8, 1.8);
+		let b = obb(1.5, 0.0, FRAC_PI_2, 0.8, 1.8);
+		assert!(a.overlaps(&b));
+		let a = obb(0.0, 0.0, 0.0, 0.8, 1.8);
+		let b = obb(1.5, 0.0, 0.0, 0.8, 1.8);
+		assert!(!a.overlaps(&b));
+	}
+
+	#[test]
+	fn rotated_corner_near_miss() {
+		// A 45 degree square whose corner points at the other square's corner. The bounding
+		// circles and axis-aligned boxes overlap but the shapes don't.
+		let a = obb(0.0, 0.0, 0.0, 1.0, 1.0);
+		let b = obb(1.15, 1.15, FRAC_PI_4, 1.0, 1.0);
+		assert!(!a.overlaps(&b));
+		// Moving it in a little makes them touch
+		let b = obb(0.8, 0.8, FRAC_PI_4, 1.0, 1.0);
+		assert!(a.overlaps(&b));
+	}
+
+	#[test]
+	fn t_bone_into_cart_side() {
+		// Truck nose pointing at the middle of a cart crossing in front of it
+		let cart = obb(0.0, 0.0, FRAC_PI_2, 0.7, 1.4);
+		let truck_hit = obb(0.0, -1.2, 0.0, 0.8, 1.8);
+		let truck_short = obb(0.0, -1.3, 0.0, 0.8, 1.8);
+		assert!(cart.overlaps(&truck_hit));
+		assert!(!cart.overlaps(&truck_short));
+	}
+
+	#[test]
+	fn heading_wraps_around() {
+		let a = obb(0.0, 0.0, 0.3, 0.8, 1.8);
+		let b = obb(0.0, 0.0, 0.3 + TAU, 0.8, 1.8);
+		let c = obb(0.5, 1.0, 0.0, 0.7, 1.4);
+		assert_eq!(a.overlaps(&c), b.overlaps(&c));
+	}
+
+	#[test]
+	fn degenerate_boxes() {
+		// Zero-size boxes behave like points (inside or outside) rather than panicking
+		let a = obb(0.0, 0.0, 0.7, 0.8, 1.8);
+		let inside = obb(0.1, 0.1, 0.0, 0.0, 0.0);
+		let outside = obb(5.0, 5.0, 0.0, 0.0, 0.0);
+		assert!(a.overlaps(&inside));
+		assert!(!a.overlaps(&outside));
+	}
+
+	#[test]
+	fn truck_and_cart_footprints_follow_config() {
+		let cfg = GameConfig::default();
+		let mut player = PlayerState {
+			id: Default::default(),
+			position: Vec3 { x: 0.0, y: 0.5, z: 0.0 },
+			rotation_y: 0.0,
+			trailer: Default::default(),
+			trailer_headings: Default::default(),
+			alive: true,
+			boost_meter: 1.0,
+		};
+		crate::physics::reset_trailer(&mut player, &cfg, &crate::MapDef::empty(cfg.world_size), 3);
+		let head = Obb::truck(&player, &cfg);
+		assert_eq!(head.half_length, cfg.truck_length * 0.5);
+		let carts: Vec<Obb> = Obb::carts(&player, &cfg).collect();
+		assert_eq!(carts.len(), 2);
+		// A straight trailer doesn't touch its own truck or overlap between carts
+		assert!(!head.overlaps(&carts[0]));
+		assert!(!carts[0].overlaps(&carts[1]));
+	}
+}
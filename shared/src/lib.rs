@@ -1,10 +1,29 @@
-use rand::Rng;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
 use uuid::Uuid;
 
+pub mod bot;
+pub mod collision;
+pub mod gym;
+pub mod map;
+pub mod physics;
+
+pub use bot::{BotBrain, BotController, BotDifficulty, BotPersonality, Observation};
+pub use collision::Obb;
+pub use map::{MapDef, MapError, Obstacle, ObstacleShape, ShrinkZone, SpawnPoint};
+
 pub type PlayerId = Uuid;
 
+// Bumped whenever client and server messages change incompatibly. Clients send it when
+// connecting (`/ws?v=<version>`) so an outdated client is told instead of misbehaving.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+// WebSocket close codes the server uses to turn a client away (4000-4999 is for applications)
+pub const CLOSE_SERVER_FULL: u16 = 4001;
+pub const CLOSE_VERSION_MISMATCH: u16 = 4002;
+pub const CLOSE_KICKED: u16 = 4003; // Kicked or banned by an admin; don't reconnect on your own
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Vec3 {
 	pub x: f32,
@@ -19,12 +38,25 @@ pub enum TurnInput {
 	Straight,
 }
 
+impl TurnInput {
+	// The equivalent analog steering value (1 = full left, -1 = full right)
+	pub fn steer(self) -> f32 {
+		match self {
+			TurnInput::Left => 1.0,
+			TurnInput::Right => -1.0,
+			TurnInput::Straight => 0.0,
+		}
+	}
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlayerState {
 	pub id: PlayerId,
 	pub position: Vec3,
 	pub rotation_y: f32, // yaw angle in radians
 	pub trailer: VecDeque<Vec3>,
+	#[serde(default)]
+	pub trailer_headings: VecDeque<f32>, // Yaw of each `trailer` entry (index 0 is the truck itself)
 	pub alive: bool,
 	pub boost_meter: f32, // Boost meter from 0.0 to 1.0
 }
@@ -38,34 +70,148 @@ pub struct Item {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WorldState {
 	pub world_size: f32, // half-size of the world
-	pub players: HashMap<PlayerId, PlayerState>,
-	pub items: HashMap<Uuid, Item>,
+	// Ordered maps, so the sim walks players and items the same way on every run and a seeded
+	// sim plays out the same
+	pub players: BTreeMap<PlayerId, PlayerState>,
+	pub items: BTreeMap<Uuid, Item>,
 	pub tick: u64,
-	pub bots: std::collections::HashSet<PlayerId>, // Track which players are bots (synchronized to clients)
+	pub bots: BTreeSet<PlayerId>, // Track which players are bots (synchronized to clients)
+	pub zone_half_size: f32, // Current playable half-size (equals world_size unless the map has a shrinking zone)
+	#[serde(default)]
+	pub spectators: u32, // Connections watching without a truck (filled in by the server)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ClientToServer {
-	Hello { name: String },
-	Input { turn: TurnInput, boost: bool },
+	// Name and colour (`#rrggbb`) to save in our profile, if we connected with one
+	Hello {
+		name: String,
+		#[serde(default)]
+		color: Option<String>,
+	},
+	Input {
+		turn: TurnInput,
+		boost: bool,
+		#[serde(default)]
+		steer: Option<f32>, // Analog steering from -1 (right) to 1 (left); overrides `turn` when present
+	},
 	Ping(u64),
+	Chat(String), // Up to MAX_CHAT_CHARS; the server filters and throttles it
+	Emote(Emote),
+}
+
+// Longest chat message, in characters
+pub const MAX_CHAT_CHARS: usize = 200;
+pub const MAX_NAME_CHARS: usize = 20;
+
+// A player's persistent profile, kept by servers that have profiles enabled
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+	pub id: Uuid, // Public; the token that signs in to it is private to the player
+	pub name: String, // Empty until the player picks one
+	pub color: Option<String>, // `#rrggbb`
+	pub stats: ProfileStats,
+}
+
+// Lifetime stats across every visit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProfileStats {
+	pub games: u64, // Times joined with a new truck
+	pub kills: u64, // Trucks that died running into this player's truck or trailer
+	pub deaths: u64,
+	pub longest_trailer: u64,
+}
+
+// Quick-chat emotes, shown above the sender's truck
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Emote {
+	Hello,
+	GoodGame,
+	Oops,
+	Thanks,
+}
+
+impl Emote {
+	pub const ALL: [Emote; 4] = [Emote::Hello, Emote::GoodGame, Emote::Oops, Emote::Thanks];
+
+	pub fn text(self) -> &'static str {
+		match self {
+			Emote::Hello => "Hi!",
+			Emote::GoodGame => "GG",
+			Emote::Oops => "Oops!",
+			Emote::Thanks => "Thanks!",
+		}
+	}
 }
 
+// Welcome, which carries the map, is sent once per connection; keeping it unboxed costs little
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ServerToClient {
-	Welcome { id: PlayerId, world_size: f32 },
+	Welcome {
+		id: PlayerId,
+		world_size: f32,
+		map: MapDef,
+		config: GameConfig, // Lets clients predict with the server's tuning
+		#[serde(default)]
+		session: String, // Token to resume this truck after a dropped connection (`/ws?resume=<token>`)
+		#[serde(default)]
+		resumed: bool, // True if this connection took back an existing truck
+		#[serde(default)]
+		spectator: bool, // Joined with `/ws?spectate=true`: `id` owns no truck and inputs are ignored
+		#[serde(default)]
+		profile: Option<Profile>, // Joined with `/ws?profile=<token>` on a server keeping profiles
+	},
 	State(WorldState),
 	Pong(u64),
 	YouDied,
+	ServerShutdown { reason: String, seconds: u32 }, // The server closes all connections in `seconds`
+	ConfigChanged(GameConfig), // Gameplay tuning was reloaded; applies from the next tick
+	ServerMessage(String), // Announcement from the server's admins
+	Chat { from: PlayerId, text: String },
+	Emote { from: PlayerId, emote: Emote },
+	ChatRejected(String), // Why the server dropped this client's last chat message or emote
 }
 
-#[derive(Debug, Clone)]
+// Why a truck died this tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DeathCause {
+	Wall, // Outer arena boundary
+	Obstacle,
+	Zone, // Outside the shrinking zone
+	Truck, // Head-on with another truck
+	Trailer, // Ran into another truck's trailer
+}
+
+// Things that happened during the last `GameSim::step`, for scoring and stats
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameEvent {
+	ItemPickup { player: PlayerId },
+	Death { player: PlayerId, cause: DeathCause, killer: Option<PlayerId>, trailer_len: usize },
+}
+
+// Gameplay tuning. Missing fields take their defaults, so config files only need the values
+// they change.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct GameConfig {
 	pub world_size: f32,
 	pub player_speed: f32,
-	pub turn_speed: f32,
+	pub turn_speed: f32, // Radians per second at full steering
+	pub boost_turn_multiplier: f32, // Turn rate while boosting relative to normal (below 1 widens boosted turns)
+	pub boost_multiplier: f32, // Speed while boosting relative to normal
+	pub boost_drain_per_sec: f32, // Fraction of a full meter used per second of boosting
+	pub boost_regen_per_sec: f32, // Fraction of a full meter regained per second when not boosting
 	pub initial_length: usize,
 	pub item_spawn_every_ticks: u64,
+	pub tick_rate: u32, // Simulation ticks per second; `step` advances 1 / tick_rate seconds
+	pub truck_length: f32,
+	pub truck_width: f32,
+	pub cart_length: f32,
+	pub cart_width: f32,
+	pub hitch_gap: f32, // Drawbar between a vehicle's hitch and the front of the cart it tows
+	pub truck_hitch_offset: f32, // Distance from the truck's centre back to its hitch
+	pub max_hitch_angle: f32, // Jackknife limit: max yaw between a cart and its tow, in radians
 }
 
 impl Default for GameConfig {
@@ -74,86 +220,201 @@ impl Default for GameConfig {
 			world_size: 128.0, // Doubled from 64.0 (full world is now 256x256)
 			player_speed: 12.0, // Doubled from 6.0
 			turn_speed: 2.5,
+			boost_turn_multiplier: 1.0,
+			// A full meter drains in 2 seconds and refills in 5
+			boost_multiplier: 2.0,
+			boost_drain_per_sec: 1.0 / 2.0,
+			boost_regen_per_sec: 1.0 / 5.0,
 			initial_length: 3,
 			item_spawn_every_ticks: 20,
+			tick_rate: 30,
+			truck_length: 1.8,
+			truck_width: 0.8,
+			cart_length: 1.4,
+			cart_width: 0.7,
+			hitch_gap: 0.8,
+			truck_hitch_offset: 0.9,
+			max_hitch_angle: 75f32.to_radians(),
+		}
+	}
+}
+
+impl GameConfig {
+	// Seconds of game time per tick
+	pub fn dt(&self) -> f32 {
+		1.0 / self.tick_rate.max(1) as f32
+	}
+
+	// Distance from a hitch point to the centre of the cart hanging off it
+	pub fn hitch_length(&self) -> f32 {
+		self.hitch_gap + self.cart_length * 0.5
+	}
+
+	// Every out-of-range value, as "field must ... (got ...)" messages
+	pub fn validate(&self) -> Result<(), Vec<String>> {
+		let mut errors = Vec::new();
+		let mut positive = |name: &str, v: f32| {
+			if !(v.is_finite() && v > 0.0) {
+				errors.push(format!("{name} must be a positive number (got {v})"));
+			}
+		};
+		positive("world_size", self.world_size);
+		positive("player_speed", self.player_speed);
+		positive("turn_speed", self.turn_speed);
+		positive("boost_turn_multiplier", self.boost_turn_multiplier);
+		positive("boost_multiplier", self.boost_multiplier);
+		positive("boost_drain_per_sec", self.boost_drain_per_sec);
+		positive("boost_regen_per_sec", self.boost_regen_per_sec);
+		positive("truck_length", self.truck_length);
+		positive("truck_width", self.truck_width);
+		positive("cart_length", self.cart_length);
+		positive("cart_width", self.cart_width);
+		if !(self.hitch_gap.is_finite() && self.hitch_gap >= 0.0) {
+			errors.push(format!("hitch_gap must be zero or more (got {})", self.hitch_gap));
+		}
+		if !(self.truck_hitch_offset.is_finite() && self.truck_hitch_offset >= 0.0) {
+			errors.push(format!("truck_hitch_offset must be zero or more (got {})", self.truck_hitch_offset));
+		}
+		if !(self.max_hitch_angle > 0.0 && self.max_hitch_angle <= std::f32::consts::PI) {
+			errors.push(format!("max_hitch_angle must be in (0, pi] radians (got {})", self.max_hitch_angle));
 		}
+		if self.initial_length == 0 {
+			errors.push("initial_length must be at least 1 (the truck itself)".to_string());
+		}
+		if self.item_spawn_every_ticks == 0 {
+			errors.push("item_spawn_every_ticks must be at least 1".to_string());
+		}
+		if !(1..=240).contains(&self.tick_rate) {
+			errors.push(format!("tick_rate must be between 1 and 240 (got {})", self.tick_rate));
+		}
+		if errors.is_empty() { Ok(()) } else { Err(errors) }
 	}
 }
 
 pub struct GameSim {
 	pub cfg: GameConfig,
+	pub map: MapDef,
 	pub state: WorldState,
-	pub pending_inputs: HashMap<PlayerId, TurnInput>,
+	pub pending_inputs: HashMap<PlayerId, f32>, // Steering per player for the next tick, -1..1
 	pub pending_boosts: HashMap<PlayerId, bool>,
-	pub bots: std::collections::HashSet<PlayerId>, // Track which players are bots
+	pub bots: BTreeSet<PlayerId>, // Track which players are bots
+	pub bot_brains: HashMap<PlayerId, BotBrain>, // Controller and difficulty driving each bot
+	pub autopilot: HashMap<PlayerId, BotBrain>, // Humans' trucks driven by a bot while their player is disconnected
+	pub target_trucks: usize, // Bots fill up to this many trucks in total and leave as humans join (0 = no auto fill)
+	pub bot_difficulty: BotDifficulty, // Difficulty given to newly added bots
+	pub events: Vec<GameEvent>, // Events from the most recent step
+	pub match_start_tick: u64, // Tick the current match started at; the shrinking zone counts from here
+	rng: StdRng, // Spawns, items and ids; reseed for reproducible runs
 }
 
 impl GameSim {
 	pub fn new(cfg: GameConfig) -> Self {
+		let map = MapDef::empty(cfg.world_size);
+		Self::with_map(cfg, map)
+	}
+
+	// Create a sim on the given arena; the map's world_size overrides the config's
+	pub fn with_map(mut cfg: GameConfig, map: MapDef) -> Self {
+		cfg.world_size = map.world_size;
 		Self {
 			state: WorldState {
 				world_size: cfg.world_size,
-				players: HashMap::new(),
-				items: HashMap::new(),
+				players: BTreeMap::new(),
+				items: BTreeMap::new(),
 				tick: 0,
-				bots: std::collections::HashSet::new(),
+				bots: BTreeSet::new(),
+				zone_half_size: cfg.world_size,
+				spectators: 0,
 			},
 			pending_inputs: HashMap::new(),
 			pending_boosts: HashMap::new(),
-			bots: std::collections::HashSet::new(),
+			bots: BTreeSet::new(),
+			bot_brains: HashMap::new(),
+			autopilot: HashMap::new(),
+			target_trucks: 0,
+			bot_difficulty: BotDifficulty::default(),
+			events: Vec::new(),
+			match_start_tick: 0,
+			rng: StdRng::from_entropy(),
+			map,
 			cfg,
 		}
 	}
 
-	pub fn add_player(&mut self) -> PlayerId {
-		let id = Uuid::new_v4();
-		let mut rng = rand::thread_rng();
-		let ws = self.cfg.world_size;
+	// Swap in new tuning between ticks. The arena keeps its size, so world_size is ignored.
+	pub fn set_config(&mut self, mut cfg: GameConfig) {
+		cfg.world_size = self.cfg.world_size;
+		self.cfg = cfg;
+	}
+
+	// Make spawns, item placement and ids reproducible from here on
+	pub fn reseed(&mut self, seed: u64) {
+		self.rng = StdRng::seed_from_u64(seed);
+	}
+
+	fn new_id(&mut self) -> Uuid {
+		uuid::Builder::from_random_bytes(self.rng.gen()).into_uuid()
+	}
+
+	// Pick a spawn position and heading. Maps with spawn points use the one furthest from
+	// living players; otherwise (or if the zone has shrunk past all of them) pick a random
+	// spot inside the zone that is clear of obstacles.
+	fn pick_spawn(&mut self) -> (Vec3, f32) {
+		let Self { rng, state, map, cfg, .. } = self;
+		let ws = state.zone_half_size.min(cfg.world_size);
+		let in_zone = |sp: &&SpawnPoint| sp.x.abs() < ws - 5.0 && sp.z.abs() < ws - 5.0;
+		if map.spawn_points.iter().any(|sp| in_zone(&sp)) {
+			let clearance = |sp: &SpawnPoint| {
+				state.players.values()
+					.filter(|p| p.alive)
+					.map(|p| {
+						let dx = p.position.x - sp.x;
+						let dz = p.position.z - sp.z;
+						dx * dx + dz * dz
+					})
+					.fold(f32::MAX, f32::min)
+			};
+			// Small jitter so equally clear spawn points are picked at random
+			let sp = map.spawn_points.iter()
+				.filter(in_zone)
+				.map(|sp| (sp, clearance(sp).min(1.0e6) + rng.gen_range(0.0..1.0)))
+				.max_by(|a, b| a.1.total_cmp(&b.1))
+				.map(|(sp, _)| *sp)
+				.unwrap();
+			return (Vec3 { x: sp.x, y: 0.5, z: sp.z }, sp.rotation_y);
+		}
+
 		// Keep players away from edges (15 unit buffer to account for trailer length)
 		let margin = 15.0;
 		let spawn_range = (ws - margin).max(5.0); // Ensure at least 5 units of spawn range
-		let position = Vec3 {
-			x: rng.gen_range(-spawn_range..spawn_range),
-			y: 0.5,
-			z: rng.gen_range(-spawn_range..spawn_range),
-		};
-		let rotation_y = rng.gen_range(0.0..std::f32::consts::TAU);
-		
-		// Initialize trailer with 2 carts (3 positions total: player + 2 carts)
-		// Calculate positions behind the player for the carts
-		// Cart spacing: cart length (1.4) + gap (0.8) = 2.2 units between cart centers
-		// Player back to first cart: player_back_offset (0.9) + gap (0.8) + cart_front_offset (0.7) = 2.4 units
-		let mut trailer = VecDeque::new();
-		trailer.push_back(position); // Current position (player)
-		
-		// Calculate backward direction from rotation
-		let backward_x = -rotation_y.sin();
-		let backward_z = -rotation_y.cos();
-		
-		// First cart position: behind player by 2.4 units (player back + gap + cart front)
-		let cart1_pos = Vec3 {
-			x: position.x + backward_x * 2.4,
-			y: 0.5,
-			z: position.z + backward_z * 2.4,
-		};
-		trailer.push_back(cart1_pos);
-		
-		// Second cart position: behind first cart by 2.2 units (cart back + gap + cart front)
-		let cart2_pos = Vec3 {
-			x: cart1_pos.x + backward_x * 2.2,
-			y: 0.5,
-			z: cart1_pos.z + backward_z * 2.2,
-		};
-		trailer.push_back(cart2_pos);
+		let mut position = Vec3 { x: 0.0, y: 0.5, z: 0.0 };
+		for _ in 0..32 {
+			position.x = rng.gen_range(-spawn_range..spawn_range);
+			position.z = rng.gen_range(-spawn_range..spawn_range);
+			// Leave room for the initial trailer behind the truck
+			if !map.blocked(position.x, position.z, 3.0) {
+				break;
+			}
+		}
+		(position, rng.gen_range(0.0..std::f32::consts::TAU))
+	}
+
+	pub fn add_player(&mut self) -> PlayerId {
+		let id = self.new_id();
+		let (position, rotation_y) = self.pick_spawn();
 		
-		self.state.players.insert(id, PlayerState { 
+		let mut player = PlayerState { 
 			id, 
 			position, 
 			rotation_y, 
-			trailer, 
+			trailer: VecDeque::new(), 
+			trailer_headings: VecDeque::new(),
 			alive: true,
 			boost_meter: 1.0, // Start with full boost
-		});
+		};
+		// Truck plus the initial carts lined up straight behind it
+		physics::reset_trailer(&mut player, &self.cfg, &self.map, self.cfg.initial_length);
+		self.state.players.insert(id, player);
 		id
 	}
 
@@ -162,32 +423,91 @@ impl GameSim {
 		self.pending_inputs.remove(id);
 		self.pending_boosts.remove(id);
 		self.bots.remove(id);
+		self.bot_brains.remove(id);
+		self.autopilot.remove(id);
 	}
 
-	// Add a bot player
+	// Add a bot player with a random built-in personality at the default difficulty
 	pub fn add_bot(&mut self) -> PlayerId {
+		let personality = BotPersonality::ALL[self.rng.gen_range(0..BotPersonality::ALL.len())];
+		self.add_bot_with(personality.controller(), self.bot_difficulty)
+	}
+
+	// Add a bot player driven by the given controller
+	pub fn add_bot_with(&mut self, controller: Box<dyn BotController>, difficulty: BotDifficulty) -> PlayerId {
 		let id = self.add_player();
 		self.bots.insert(id);
+		let seed = self.rng.gen();
+		self.bot_brains.insert(id, BotBrain::with_seed(controller, difficulty, seed));
 		id
 	}
 
+	// Swap the controller of an existing bot; returns false if `id` isn't a bot
+	pub fn set_bot_controller(&mut self, id: &PlayerId, controller: Box<dyn BotController>) -> bool {
+		match self.bot_brains.get_mut(id) {
+			Some(brain) => {
+				brain.controller = controller;
+				true
+			}
+			None => false,
+		}
+	}
+
+	// Change the difficulty of an existing bot; returns false if `id` isn't a bot
+	pub fn set_bot_difficulty(&mut self, id: &PlayerId, difficulty: BotDifficulty) -> bool {
+		match self.bot_brains.get_mut(id) {
+			Some(brain) => {
+				brain.difficulty = difficulty.clamped();
+				true
+			}
+			None => false,
+		}
+	}
+
+	// Let a cautious bot drive a human's truck (while they reconnect), or hand it back.
+	// Autopiloted trucks still count as humans for bot balancing.
+	pub fn set_autopilot(&mut self, id: &PlayerId, enabled: bool) {
+		if !enabled {
+			self.autopilot.remove(id);
+		} else if self.state.players.contains_key(id) && !self.bots.contains(id) {
+			let seed = self.rng.gen();
+			let brain = BotBrain::with_seed(BotPersonality::Coward.controller(), BotDifficulty::NORMAL, seed);
+			self.autopilot.insert(*id, brain);
+		}
+	}
+
+	pub fn human_count(&self) -> usize {
+		self.state.players.keys().filter(|id| !self.bots.contains(id)).count()
+	}
+
+	// Add or remove bots so humans + bots == target_trucks. Dead bots are removed first.
+	pub fn balance_bots(&mut self) {
+		if self.target_trucks == 0 {
+			return;
+		}
+		let wanted = self.target_trucks.saturating_sub(self.human_count());
+		while self.bots.len() < wanted {
+			self.add_bot();
+		}
+		while self.bots.len() > wanted {
+			let victim = self.bots.iter()
+				.copied()
+				.min_by_key(|id| self.state.players.get(id).is_some_and(|p| p.alive));
+			match victim {
+				Some(id) => self.remove_player(&id),
+				None => break,
+			}
+		}
+	}
+
 	pub fn respawn_player(&mut self, id: &PlayerId) {
+		let (position, rotation_y) = self.pick_spawn();
 		if let Some(player) = self.state.players.get_mut(id) {
-			let mut rng = rand::thread_rng();
-			let ws = self.cfg.world_size;
-			// Keep players away from edges (15 unit buffer to account for trailer length)
-			let margin = 15.0;
-			let spawn_range = (ws - margin).max(5.0); // Ensure at least 5 units of spawn range
-			// Respawn at random position
-			player.position = Vec3 {
-				x: rng.gen_range(-spawn_range..spawn_range),
-				y: 0.5,
-				z: rng.gen_range(-spawn_range..spawn_range),
-			};
-			player.rotation_y = rng.gen_range(0.0..std::f32::consts::TAU);
+			// Respawn at a fresh spawn location
+			player.position = position;
+			player.rotation_y = rotation_y;
 			// Reset trailer to just the player position (no cubes)
-			player.trailer.clear();
-			player.trailer.push_back(player.position);
+			physics::reset_trailer(player, &self.cfg, &self.map, 1);
 			player.alive = true;
 			// Reset boost state
 			player.boost_meter = 1.0; // Reset to full boost
@@ -197,172 +517,107 @@ impl GameSim {
 		}
 	}
 
+	// Start a new match in place: items are cleared, the zone opens up again and every truck
+	// respawns at its initial length. Players, bots and the tick counter carry on.
+	pub fn restart_match(&mut self) {
+		self.match_start_tick = self.state.tick;
+		self.state.zone_half_size = self.map.zone_half_size(0.0);
+		self.state.items.clear();
+		let ids: Vec<PlayerId> = self.state.players.keys().copied().collect();
+		for id in ids {
+			self.respawn_player(&id);
+			if let Some(player) = self.state.players.get_mut(&id) {
+				physics::reset_trailer(player, &self.cfg, &self.map, self.cfg.initial_length);
+			}
+		}
+	}
+
 	pub fn submit_input(&mut self, id: PlayerId, input: TurnInput) {
-		self.pending_inputs.insert(id, input);
+		self.pending_inputs.insert(id, input.steer());
+	}
+
+	// Analog steering, clamped to -1..1; non-finite values steer straight
+	pub fn submit_steer(&mut self, id: PlayerId, steer: f32) {
+		let steer = if steer.is_finite() { steer.clamp(-1.0, 1.0) } else { 0.0 };
+		self.pending_inputs.insert(id, steer);
 	}
 
 	pub fn submit_boost(&mut self, id: PlayerId, boost: bool) {
 		self.pending_boosts.insert(id, boost);
 	}
 
-	fn wrap(&self, pos: Vec3) -> Vec3 {
-		let ws = self.cfg.world_size;
-		let wrap = |v: f32| {
-			let mut r = v;
-			if r < -ws { r = ws; }
-			if r > ws { r = -ws; }
-			r
-		};
-		Vec3 { x: wrap(pos.x), y: pos.y, z: wrap(pos.z) }
+	fn spawn_item(&mut self) {
+		// Only spawn inside the playable zone and away from solid obstacles
+		let ws = (self.state.zone_half_size - 1.0).max(1.0);
+		for _ in 0..8 {
+			let pos = Vec3 {
+				x: self.rng.gen_range(-ws..ws),
+				y: 0.3,
+				z: self.rng.gen_range(-ws..ws),
+			};
+			if self.map.blocked(pos.x, pos.z, 0.6) {
+				continue;
+			}
+			let id = self.new_id();
+			self.state.items.insert(id, Item { pos, id });
+			return;
+		}
 	}
 
-	fn spawn_item(&mut self) {
-		let mut rng = rand::thread_rng();
-		let ws = self.cfg.world_size;
-		let pos = Vec3 {
-			x: rng.gen_range(-ws..ws),
-			y: 0.3,
-			z: rng.gen_range(-ws..ws),
-		};
-		let id = Uuid::new_v4();
-		self.state.items.insert(id, Item { pos, id });
+	// The brain's (steer, boost) for `id` this tick, if it is alive
+	fn think_for(&self, id: PlayerId, brain: &mut BotBrain, dt: f32) -> Option<(f32, bool)> {
+		let mut obs = Observation::new(self, id)?;
+		obs.aggression = brain.difficulty.aggression;
+		let (turn, boost) = brain.think(&obs, dt);
+		Some((turn.steer(), boost))
 	}
 
 	pub fn step(&mut self) {
 		self.state.tick += 1;
-		let dt = 1.0 / 30.0; // 33ms tick ≈ 0.033 seconds (30 TPS)
+		self.events.clear();
+		let dt = self.cfg.dt();
 		let world_size = self.cfg.world_size;
 		
+		// Advance the shrinking zone (if the map has one)
+		self.state.zone_half_size = self.map.zone_half_size((self.state.tick - self.match_start_tick) as f32 * dt);
+		let zone_half_size = self.state.zone_half_size;
+		
 		// Apply inputs and move players
 		for player in self.state.players.values_mut() {
 			if !player.alive { continue; }
 			
-			// Handle boost input and update boost meter
+			let steer = self.pending_inputs.remove(&player.id).unwrap_or(0.0);
 			let boost_pressed = self.pending_boosts.remove(&player.id).unwrap_or(false);
-			let boost_active = boost_pressed && player.boost_meter > 0.0;
-			
-			if boost_active {
-				// Deplete boost meter while boosting (depletes in 2 seconds at full speed)
-				let deplete_rate = 1.0 / 2.0; // Deplete full meter in 2 seconds
-				player.boost_meter -= deplete_rate * dt;
-				if player.boost_meter < 0.0 {
-					player.boost_meter = 0.0;
-				}
-			} else {
-				// Regenerate boost meter slowly when not boosting (regenerates in 5 seconds)
-				let regen_rate = 1.0 / 5.0; // Regenerate full meter in 5 seconds
-				player.boost_meter += regen_rate * dt;
-				if player.boost_meter > 1.0 {
-					player.boost_meter = 1.0;
-				}
-			}
-			
-			// Apply turn input
-			if let Some(input) = self.pending_inputs.remove(&player.id) {
-				use TurnInput::*;
-				match input {
-					Left => player.rotation_y += self.cfg.turn_speed * dt,
-					Right => player.rotation_y -= self.cfg.turn_speed * dt,
-					Straight => {}
-				}
-			}
-			
-			// Auto-forward movement with boost multiplier
-			let boost_active = boost_pressed && player.boost_meter > 0.0;
-			let speed_multiplier = if boost_active { 2.0 } else { 1.0 };
-			let forward_x = player.rotation_y.sin();
-			let forward_z = player.rotation_y.cos();
-			player.position.x += forward_x * self.cfg.player_speed * speed_multiplier * dt;
-			player.position.z += forward_z * self.cfg.player_speed * speed_multiplier * dt;
+			physics::advance_player(player, steer, boost_pressed, &self.cfg, dt);
 			
-			// Check wall collisions - kill player if they hit the boundary
-			// Player radius is approximately 0.5 (half of 1.0 cube size, but we use 0.9 for truck shape)
-			let player_radius = 0.5;
-			if player.position.x <= -world_size + player_radius || 
-			   player.position.x >= world_size - player_radius ||
-			   player.position.z <= -world_size + player_radius ||
-			   player.position.z >= world_size - player_radius {
+			// Walls, solid obstacles and leaving the shrinking zone are all lethal
+			if let Some(cause) = physics::resolve_environment(player, &self.map, world_size, zone_half_size) {
 				player.alive = false;
-			} else {
-				// Clamp position to keep player within bounds (prevent going slightly past wall)
-				player.position.x = player.position.x.clamp(-world_size + player_radius, world_size - player_radius);
-				player.position.z = player.position.z.clamp(-world_size + player_radius, world_size - player_radius);
+				self.events.push(GameEvent::Death { player: player.id, cause, killer: None, trailer_len: player.trailer.len() });
 			}
-			player.position.y = 0.5; // Maintain hover height
 		}
 		
-		// Bot AI: make bots move towards nearest items
-		for (bot_id, player) in self.state.players.iter_mut() {
-			if !self.bots.contains(bot_id) || !player.alive {
-				continue;
+		// Bot AI: each bot's controller sees the world and picks its input for this tick.
+		// Brains are taken out while deciding so they can borrow the sim immutably.
+		let mut brains = std::mem::take(&mut self.bot_brains);
+		for &bot_id in &self.bots {
+			let brain = brains
+				.entry(bot_id)
+				.or_insert_with(|| BotBrain::new(BotPersonality::Collector.controller(), self.bot_difficulty));
+			if let Some((steer, boost)) = self.think_for(bot_id, brain, dt) {
+				self.pending_inputs.insert(bot_id, steer);
+				self.pending_boosts.insert(bot_id, boost);
 			}
-			
-			// Find nearest item
-			let mut nearest_item: Option<(Uuid, Vec3, f32)> = None;
-			for (item_id, item) in &self.state.items {
-				let dx = item.pos.x - player.position.x;
-				let dz = item.pos.z - player.position.z;
-				let dist_sq = dx * dx + dz * dz;
-				if let Some((_, _, nearest_dist_sq)) = nearest_item {
-					if dist_sq < nearest_dist_sq {
-						nearest_item = Some((*item_id, item.pos, dist_sq));
-					}
-				} else {
-					nearest_item = Some((*item_id, item.pos, dist_sq));
-				}
-			}
-			
-			if let Some((_item_id, target_pos, dist_sq)) = nearest_item {
-				// Calculate desired direction to item
-				let dx = target_pos.x - player.position.x;
-				let dz = target_pos.z - player.position.z;
-				let dist = dist_sq.sqrt();
-				
-				if dist > 0.1 {
-					let target_dir = Vec3 { x: dx / dist, y: 0.0, z: dz / dist };
-					
-					// Calculate current forward direction
-					let forward_x = player.rotation_y.sin();
-					let forward_z = player.rotation_y.cos();
-					let current_forward = Vec3 { x: forward_x, y: 0.0, z: forward_z };
-					
-					// Calculate angle difference (using cross product to determine left/right)
-					let cross = current_forward.x * target_dir.z - current_forward.z * target_dir.x;
-					
-					// Determine turn direction
-					let turn_threshold = 0.1; // Turn if more than this angle off
-					if cross.abs() > turn_threshold {
-						if cross > 0.0 {
-							// Turn left
-							self.pending_inputs.insert(*bot_id, TurnInput::Left);
-						} else {
-							// Turn right
-							self.pending_inputs.insert(*bot_id, TurnInput::Right);
-						}
-					} else {
-						// Go straight if aligned
-						self.pending_inputs.insert(*bot_id, TurnInput::Straight);
-					}
-					
-					// Use boost if item is far and boost is available
-					if dist > 15.0 && player.boost_meter > 0.3 {
-						self.pending_boosts.insert(*bot_id, true);
-					}
-				}
-			} else {
-				// No items, wander randomly
-				let mut rng = rand::thread_rng();
-				if rng.gen_bool(0.1) { // 10% chance to turn each tick
-					if rng.gen_bool(0.5) {
-						self.pending_inputs.insert(*bot_id, TurnInput::Left);
-					} else {
-						self.pending_inputs.insert(*bot_id, TurnInput::Right);
-					}
-				} else {
-					self.pending_inputs.insert(*bot_id, TurnInput::Straight);
-				}
+		}
+		self.bot_brains = brains;
+		let mut autopilot = std::mem::take(&mut self.autopilot);
+		for (&id, brain) in autopilot.iter_mut() {
+			if let Some((steer, boost)) = self.think_for(id, brain, dt) {
+				self.pending_inputs.insert(id, steer);
+				self.pending_boosts.insert(id, boost);
 			}
 		}
+		self.autopilot = autopilot;
 		
 		// Check items and update trailers
 		let mut items_to_remove = Vec::new();
@@ -397,153 +652,13 @@ impl GameSim {
 				current_length.max(min_length) // Ensure at least minimum length
 			};
 			
-			// Update trailer - store actual cart positions, not just historical player positions
-			// Calculate current cart positions based on physics
-			let gap = 0.8;
-			let player_back_offset = 0.9;
-			let cart_front_offset = 0.7;
-			let cart_back_offset = 0.7;
-			let hitch_length = gap + cart_front_offset;
-			
-			let player_forward = Vec3 {
-				x: player.rotation_y.sin(),
-				y: 0.0,
-				z: player.rotation_y.cos(),
-			};
-			
-			// Calculate new cart positions based on current player state
-			let mut new_trailer = VecDeque::new();
-			new_trailer.push_back(player.position); // First element is always player position
-			
-			// Calculate positions for existing carts (target_length - 1 because we already added player position)
-			let num_carts = target_length - 1;
-			if num_carts > 0 {
-				// Get previous cart positions for direction calculation
-				let mut prev_cart_pos: Option<Vec3> = None;
-				let mut prev_cart_forward: Option<Vec3> = None;
-				
-				// Use previous trailer positions to get direction, but recalculate actual positions
-				let mut old_trailer_iter = player.trailer.iter().skip(1);
-				
-				for order in 0..num_carts {
-					let old_pos = old_trailer_iter.next();
-					
-					let (cart_pos, cart_forward) = if order == 0 {
-						// First cart: attached to player
-						let hitch_point = Vec3 {
-							x: player.position.x - player_forward.x * player_back_offset,
-							y: 0.5,
-							z: player.position.z - player_forward.z * player_back_offset,
-						};
-						
-						// Use old position to determine direction if available
-						if let Some(&old_cart_pos) = old_pos {
-							let to_hitch = Vec3 {
-								x: hitch_point.x - old_cart_pos.x,
-								y: 0.0,
-								z: hitch_point.z - old_cart_pos.z,
-							};
-							let to_hitch_dist = (to_hitch.x * to_hitch.x + to_hitch.z * to_hitch.z).sqrt();
-							
-							if to_hitch_dist > 0.001 {
-								let to_hitch_dir = Vec3 {
-									x: to_hitch.x / to_hitch_dist,
-									y: 0.0,
-									z: to_hitch.z / to_hitch_dist,
-								};
-								let cart_pos = Vec3 {
-									x: hitch_point.x - to_hitch_dir.x * hitch_length,
-									y: 0.5,
-									z: hitch_point.z - to_hitch_dir.z * hitch_length,
-								};
-								(cart_pos, to_hitch_dir)
-							} else {
-								let backward = Vec3 { x: -player_forward.x, y: 0.0, z: -player_forward.z };
-								let cart_pos = Vec3 {
-									x: hitch_point.x + backward.x * hitch_length,
-									y: 0.5,
-									z: hitch_point.z + backward.z * hitch_length,
-								};
-								(cart_pos, player_forward)
-							}
-						} else {
-							let backward = Vec3 { x: -player_forward.x, y: 0.0, z: -player_forward.z };
-							let cart_pos = Vec3 {
-								x: hitch_point.x + backward.x * hitch_length,
-								y: 0.5,
-								z: hitch_point.z + backward.z * hitch_length,
-							};
-							(cart_pos, player_forward)
-						}
-					} else {
-						// Subsequent carts
-						if let (Some(prev_pos), Some(prev_fwd)) = (prev_cart_pos, prev_cart_forward) {
-							let hitch_point = Vec3 {
-								x: prev_pos.x - prev_fwd.x * cart_back_offset,
-								y: 0.5,
-								z: prev_pos.z - prev_fwd.z * cart_back_offset,
-							};
-							
-							if let Some(&old_cart_pos) = old_pos {
-								let to_hitch = Vec3 {
-									x: hitch_point.x - old_cart_pos.x,
-									y: 0.0,
-									z: hitch_point.z - old_cart_pos.z,
-								};
-								let to_hitch_dist = (to_hitch.x * to_hitch.x + to_hitch.z * to_hitch.z).sqrt();
-								
-								if to_hitch_dist > 0.001 {
-									let to_hitch_dir = Vec3 {
-										x: to_hitch.x / to_hitch_dist,
-										y: 0.0,
-										z: to_hitch.z / to_hitch_dist,
-									};
-									let cart_pos = Vec3 {
-										x: hitch_point.x - to_hitch_dir.x * hitch_length,
-										y: 0.5,
-										z: hitch_point.z - to_hitch_dir.z * hitch_length,
-									};
-									(cart_pos, to_hitch_dir)
-								} else {
-									let backward = Vec3 { x: -prev_fwd.x, y: 0.0, z: -prev_fwd.z };
-									let cart_pos = Vec3 {
-										x: hitch_point.x + backward.x * hitch_length,
-										y: 0.5,
-										z: hitch_point.z + backward.z * hitch_length,
-									};
-									(cart_pos, prev_fwd)
-								}
-							} else {
-								let backward = Vec3 { x: -prev_fwd.x, y: 0.0, z: -prev_fwd.z };
-								let cart_pos = Vec3 {
-									x: hitch_point.x + backward.x * hitch_length,
-									y: 0.5,
-									z: hitch_point.z + backward.z * hitch_length,
-								};
-								(cart_pos, prev_fwd)
-							}
-						} else {
-							// Fallback
-							let backward = Vec3 { x: -player_forward.x, y: 0.0, z: -player_forward.z };
-							let cart_pos = Vec3 {
-								x: player.position.x + backward.x * hitch_length * (order as f32 + 1.0),
-								y: 0.5,
-								z: player.position.z + backward.z * hitch_length * (order as f32 + 1.0),
-							};
-							(cart_pos, player_forward)
-						}
-					};
-					
-					new_trailer.push_back(cart_pos);
-					prev_cart_pos = Some(cart_pos);
-					prev_cart_forward = Some(cart_forward);
-				}
-			}
-			
-			// Update trailer with new positions
-			player.trailer = new_trailer;
+			// Recompute cart positions behind the truck
+			physics::update_trailer(player, &self.cfg, &self.map, target_length);
 			
 			player_grew.insert(player.id, consumed);
+			if consumed {
+				self.events.push(GameEvent::ItemPickup { player: player.id });
+			}
 		}
 		
 		// Remove consumed items
@@ -551,52 +666,35 @@ impl GameSim {
 			self.state.items.remove(&iid);
 		}
 		
-		// Check collisions between players and trailers
-		// The trailer VecDeque now stores actual cart positions (calculated above)
-		// Players die if they collide with another player OR another player's trailer segments
-		let player_data: Vec<(PlayerId, Vec3, VecDeque<Vec3>)> = self.state.players.iter()
+		// Check collisions between players and trailers using the trucks' and carts' footprints
+		// Players die if their head touches another player's head OR any of its carts
+		let footprints: Vec<(PlayerId, Obb, Vec<Obb>)> = self.state.players.iter()
 			.filter(|(_, p)| p.alive)
-			.map(|(id, p)| (*id, p.position, p.trailer.clone()))
+			.map(|(id, p)| (*id, Obb::truck(p, &self.cfg), Obb::carts(p, &self.cfg).collect()))
 			.collect();
 		
-		let mut players_to_kill = Vec::new();
-		for (player_id, player_pos, _) in &player_data {
-			for (other_id, other_pos, other_trailer) in &player_data {
+		// (victim, cause, killer)
+		let mut players_to_kill: Vec<(PlayerId, DeathCause, PlayerId)> = Vec::new();
+		for (player_id, head, _) in &footprints {
+			for (other_id, other_head, other_carts) in &footprints {
 				if *player_id == *other_id { continue; }
 				
-				// Check collision with other player directly (player-to-player collision)
-				let dx = player_pos.x - other_pos.x;
-				let dz = player_pos.z - other_pos.z;
-				let dist_sq = dx * dx + dz * dz;
-				let player_collision_dist = 0.5 + 0.5; // Both players have radius 0.5
-				if dist_sq <= player_collision_dist * player_collision_dist {
-					players_to_kill.push(*player_id);
-					continue;
+				if head.overlaps(other_head) {
+					players_to_kill.push((*player_id, DeathCause::Truck, *other_id));
+					break;
 				}
-				
-				// Check collision with other player's trailer cart positions
-				// Skip the first element (index 0) as that's the player's own position
-				for (order, &cart_pos) in other_trailer.iter().enumerate() {
-					if order == 0 { continue; } // Skip player's own position
-					
-					let dx = player_pos.x - cart_pos.x;
-					let dz = player_pos.z - cart_pos.z;
-					let dist_sq = dx * dx + dz * dz;
-					// Player radius (0.5) + trailer cart radius (0.35, cart is 0.7 wide)
-					let trailer_collision_dist = 0.5 + 0.35;
-					if dist_sq <= trailer_collision_dist * trailer_collision_dist {
-						players_to_kill.push(*player_id);
-						break; // Only need to detect one collision per other player
-					}
+				if other_carts.iter().any(|cart| head.overlaps(cart)) {
+					players_to_kill.push((*player_id, DeathCause::Trailer, *other_id));
+					break;
 				}
-				if players_to_kill.contains(player_id) { break; }
 			}
 		}
 		
 		// Kill players that collided
-		for player_id in players_to_kill {
+		for (player_id, cause, killer) in players_to_kill {
 			if let Some(player) = self.state.players.get_mut(&player_id) {
 				player.alive = false;
+				self.events.push(GameEvent::Death { player: player_id, cause, killer: Some(killer), trailer_len: player.trailer.len() });
 			}
 		}
 		
@@ -617,9 +715,38 @@ impl GameSim {
 		}
 		
 		// Periodic spawn
-		if self.state.tick % self.cfg.item_spawn_every_ticks == 0 {
+		if self.state.tick.is_multiple_of(self.cfg.item_spawn_every_ticks) {
 			self.spawn_item();
 		}
 	}
 }
 
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// Bots on the default arena, everything drawn from `seed`
+	fn seeded_sim(seed: u64) -> GameSim {
+		let mut sim = GameSim::new(GameConfig::default());
+		sim.reseed(seed);
+		for _ in 0..6 {
+			sim.add_bot();
+		}
+		sim
+	}
+
+	#[test]
+	fn same_seed_plays_out_the_same() {
+		let (mut a, mut b) = (seeded_sim(3), seeded_sim(3));
+		let mut deaths = 0;
+		for _ in 0..1500 {
+			a.step();
+			b.step();
+			deaths += a.events.iter().filter(|e| matches!(e, GameEvent::Death { .. })).count();
+		}
+		// Respawns draw from the rng too, so the run should have some
+		assert!(deaths > 0);
+		assert_eq!(serde_json::to_string(&a.state).unwrap(), serde_json::to_string(&b.state).unwrap());
+	}
+}
@@ -0,0 +1,220 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// Arena definition loaded by the server and sent to clients on join.
+// Coordinates use the same convention as the sim: forward for a yaw of `rotation_y`
+// is (sin, cos) on the XZ plane, and the world spans -world_size..world_size.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MapDef {
+	#[serde(default)]
+	pub name: String,
+	pub world_size: f32, // half-size of the arena
+	#[serde(default)]
+	pub obstacles: Vec<Obstacle>,
+	#[serde(default)]
+	pub spawn_points: Vec<SpawnPoint>,
+	#[serde(default)]
+	pub shrink_zone: Option<ShrinkZone>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SpawnPoint {
+	pub x: f32,
+	pub z: f32,
+	#[serde(default)]
+	pub rotation_y: f32,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Obstacle {
+	pub x: f32,
+	pub z: f32,
+	#[serde(default)]
+	pub rotation_y: f32,
+	pub shape: ObstacleShape,
+}
+
+// Width is measured along the obstacle's local X axis, length along its local Z (forward) axis.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum ObstacleShape {
+	Pillar { radius: f32, height: f32 },
+	Wall { width: f32, length: f32, height: f32 },
+	// Ramps are driveable: they rise from 0 at the back edge to `height` at the front edge
+	Ramp { width: f32, length: f32, height: f32 },
+}
+
+// Battle-royale boundary: after `delay_secs` the playable square shrinks linearly from the full
+// world down to `min_half_size` over `shrink_secs`, holds for `hold_secs`, then resets.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ShrinkZone {
+	pub delay_secs: f32,
+	pub shrink_secs: f32,
+	pub hold_secs: f32,
+	pub min_half_size: f32,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MapError {
+	#[error("failed to read map file: {0}")]
+	Io(#[from] std::io::Error),
+	#[error("invalid JSON map: {0}")]
+	Json(#[from] serde_json::Error),
+	#[error("invalid RON map: {0}")]
+	Ron(#[from] ron::error::SpannedError),
+	#[error("invalid map: {0}")]
+	Invalid(String),
+}
+
+impl MapDef {
+	// Empty arena of the given half-size (the pre-map behaviour)
+	pub fn empty(world_size: f32) -> Self {
+		Self {
+			name: "Empty".to_string(),
+			world_size,
+			obstacles: Vec::new(),
+			spawn_points: Vec::new(),
+			shrink_zone: None,
+		}
+	}
+
+	// Load a map from disk; `.ron` files are parsed as RON, everything else as JSON
+	pub fn load(path: impl AsRef<Path>) -> Result<Self, MapError> {
+		let path = path.as_ref();
+		let text = std::fs::read_to_string(path)?;
+		let map: MapDef = match path.extension().and_then(|e| e.to_str()) {
+			Some("ron") => ron::from_str(&text)?,
+			_ => serde_json::from_str(&text)?,
+		};
+		map.validate()?;
+		Ok(map)
+	}
+
+	pub fn to_json_pretty(&self) -> String {
+		serde_json::to_string_pretty(self).unwrap_or_default()
+	}
+
+	pub fn validate(&self) -> Result<(), MapError> {
+		if self.world_size.is_nan() || self.world_size <= 5.0 {
+			return Err(MapError::Invalid(format!("world_size must be greater than 5, got {}", self.world_size)));
+		}
+		for (i, obstacle) in self.obstacles.iter().enumerate() {
+			let positive = match obstacle.shape {
+				ObstacleShape::Pillar { radius, height } => radius > 0.0 && height > 0.0,
+				ObstacleShape::Wall { width, length, height } | ObstacleShape::Ramp { width, length, height } => {
+					width > 0.0 && length > 0.0 && height > 0.0
+				}
+			};
+			if !positive {
+				return Err(MapError::Invalid(format!("obstacle {i} has non-positive dimensions")));
+			}
+			if obstacle.x.abs() > self.world_size || obstacle.z.abs() > self.world_size {
+				return Err(MapError::Invalid(format!("obstacle {i} is outside the arena")));
+			}
+		}
+		for (i, spawn) in self.spawn_points.iter().enumerate() {
+			if spawn.x.abs() >= self.world_size || spawn.z.abs() >= self.world_size {
+				return Err(MapError::Invalid(format!("spawn point {i} is outside the arena")));
+			}
+			if self.blocked(spawn.x, spawn.z, 0.5) {
+				return Err(MapError::Invalid(format!("spawn point {i} is inside an obstacle")));
+			}
+		}
+		if let Some(zone) = &self.shrink_zone {
+			if zone.min_half_size <= 0.0 || zone.min_half_size > self.world_size {
+				return Err(MapError::Invalid("shrink_zone.min_half_size must be within (0, world_size]".to_string()));
+			}
+			if zone.delay_secs < 0.0 || zone.shrink_secs <= 0.0 || zone.hold_secs < 0.0 {
+				return Err(MapError::Invalid("shrink_zone timings must be non-negative (shrink_secs > 0)".to_string()));
+			}
+		}
+		Ok(())
+	}
+
+	// True if a circle at (x, z) overlaps any solid obstacle
+	pub fn blocked(&self, x: f32, z: f32, radius: f32) -> bool {
+		self.obstacles.iter().any(|o| o.overlaps_circle(x, z, radius))
+	}
+
+	// Hover surface height at (x, z): the highest ramp under the point, or 0
+	pub fn ground_height(&self, x: f32, z: f32) -> f32 {
+		self.obstacles
+			.iter()
+			.filter_map(|o| o.ramp_height_at(x, z))
+			.fold(0.0, f32::max)
+	}
+
+	// Current half-size of the playable area after `elapsed_secs` of play
+	pub fn zone_half_size(&self, elapsed_secs: f32) -> f32 {
+		match &self.shrink_zone {
+			Some(zone) => zone.half_size_at(elapsed_secs, self.world_size),
+			None => self.world_size,
+		}
+	}
+}
+
+impl ShrinkZone {
+	pub fn half_size_at(&self, elapsed_secs: f32, world_size: f32) -> f32 {
+		let cycle = self.delay_secs + self.shrink_secs + self.hold_secs;
+		let t = if cycle > 0.0 { elapsed_secs % cycle } else { 0.0 };
+		if t < self.delay_secs {
+			world_size
+		} else if t < self.delay_secs + self.shrink_secs {
+			let progress = (t - self.delay_secs) / self.shrink_secs;
+			world_size + (self.min_half_size - world_size) * progress
+		} else {
+			self.min_half_size
+		}
+	}
+}
+
+impl Obstacle {
+	// Convert a world point into this obstacle's local frame (x = right, z = forward)
+	pub fn to_local(&self, x: f32, z: f32) -> (f32, f32) {
+		let dx = x - self.x;
+		let dz = z - self.z;
+		let (sin, cos) = self.rotation_y.sin_cos();
+		(dx * cos - dz * sin, dx * sin + dz * cos)
+	}
+
+	pub fn is_solid(&self) -> bool {
+		!matches!(self.shape, ObstacleShape::Ramp { .. })
+	}
+
+	pub fn height(&self) -> f32 {
+		match self.shape {
+			ObstacleShape::Pillar { height, .. } | ObstacleShape::Wall { height, .. } | ObstacleShape::Ramp { height, .. } => height,
+		}
+	}
+
+	// Circle-vs-shape overlap test; ramps never block
+	pub fn overlaps_circle(&self, x: f32, z: f32, radius: f32) -> bool {
+		match self.shape {
+			ObstacleShape::Pillar { radius: r, .. } => {
+				let dx = x - self.x;
+				let dz = z - self.z;
+				dx * dx + dz * dz <= (r + radius) * (r + radius)
+			}
+			ObstacleShape::Wall { width, length, .. } => {
+				let (lx, lz) = self.to_local(x, z);
+				// Closest point on the box to the circle centre
+				let cx = lx.clamp(-width / 2.0, width / 2.0);
+				let cz = lz.clamp(-length / 2.0, length / 2.0);
+				let dx = lx - cx;
+				let dz = lz - cz;
+				dx * dx + dz * dz <= radius * radius
+			}
+			ObstacleShape::Ramp { .. } => false,
+		}
+	}
+
+	pub fn ramp_height_at(&self, x: f32, z: f32) -> Option<f32> {
+		let ObstacleShape::Ramp { width, length, height } = self.shape else {
+			return None;
+		};
+		let (lx, lz) = self.to_local(x, z);
+		if lx.abs() > width / 2.0 || lz.abs() > length / 2.0 {
+			return None;
+		}
+		Some(height * (lz + length / 2.0) / length)
+	}
+}
@@ -0,0 +1,115 @@
+// Truck movement, boost and trailer kinematics. Pure functions shared by the server sim and
+// client prediction so both sides move trucks identically.
+
+use std::collections::VecDeque;
+use std::f32::consts::{PI, TAU};
+
+use crate::{DeathCause, GameConfig, MapDef, PlayerState, Vec3};
+
+// Radius of the truck head against walls and obstacles (trucks and carts collide as boxes,
+// see `collision`)
+pub const TRUCK_RADIUS: f32 = 0.5;
+
+// Hover height above the ground
+pub const HOVER_HEIGHT: f32 = 0.5;
+
+// Apply one tick of boost, steering (-1 = full right .. 1 = full left) and forward motion
+pub fn advance_player(player: &mut PlayerState, steer: f32, boost_pressed: bool, cfg: &GameConfig, dt: f32) {
+	// Boost meter drains while boosting and regenerates otherwise
+	if boost_pressed && player.boost_meter > 0.0 {
+		player.boost_meter = (player.boost_meter - cfg.boost_drain_per_sec * dt).max(0.0);
+	} else {
+		player.boost_meter = (player.boost_meter + cfg.boost_regen_per_sec * dt).min(1.0);
+	}
+	// Boosting on the last drop of the meter doesn't count
+	let boost_active = boost_pressed && player.boost_meter > 0.0;
+
+	player.rotation_y += steer.clamp(-1.0, 1.0) * turn_rate(cfg, boost_active) * dt;
+
+	// Auto-forward movement
+	let speed = cfg.player_speed * if boost_active { cfg.boost_multiplier } else { 1.0 };
+	player.position.x += player.rotation_y.sin() * speed * dt;
+	player.position.z += player.rotation_y.cos() * speed * dt;
+}
+
+// Full-lock turn rate in radians per second
+pub fn turn_rate(cfg: &GameConfig, boosting: bool) -> f32 {
+	cfg.turn_speed * if boosting { cfg.boost_turn_multiplier } else { 1.0 }
+}
+
+// Keep the truck inside the arena, lift it onto ramps, and report what kills it at its
+// current position (outer wall, solid obstacle or the shrinking zone), if anything
+pub fn resolve_environment(player: &mut PlayerState, map: &MapDef, world_size: f32, zone_half_size: f32) -> Option<DeathCause> {
+	let limit = world_size - TRUCK_RADIUS;
+	let mut cause = None;
+	if player.position.x.abs() >= limit || player.position.z.abs() >= limit {
+		cause = Some(DeathCause::Wall);
+	}
+	// Clamp so nothing ends up past the wall, even for a frame
+	player.position.x = player.position.x.clamp(-limit, limit);
+	player.position.z = player.position.z.clamp(-limit, limit);
+
+	if cause.is_none() && map.blocked(player.position.x, player.position.z, TRUCK_RADIUS) {
+		cause = Some(DeathCause::Obstacle);
+	}
+	if cause.is_none() && (player.position.x.abs() > zone_half_size || player.position.z.abs() > zone_half_size) {
+		cause = Some(DeathCause::Zone);
+	}
+
+	player.position.y = HOVER_HEIGHT + map.ground_height(player.position.x, player.position.z);
+	cause
+}
+
+// Rebuild the trailer behind the truck with `target_length` entries (the truck itself plus
+// target_length - 1 carts). Each cart is towed by the vehicle in front of it: its front sits
+// `hitch_gap` behind that vehicle's hitch and it turns to face the hitch from where it was, so
+// carts track inside the truck's turn like a real articulated trailer. The angle between a cart
+// and whatever tows it never exceeds `max_hitch_angle`. New carts line up straight behind.
+// Carts hover over the map's ground like the truck, so they ride up ramps too.
+pub fn update_trailer(player: &mut PlayerState, cfg: &GameConfig, map: &MapDef, target_length: usize) {
+	let target_length = target_length.max(1);
+	let mut trailer = VecDeque::with_capacity(target_length);
+	let mut headings = VecDeque::with_capacity(target_length);
+	trailer.push_back(player.position); // First element is always the truck
+	headings.push_back(player.rotation_y);
+
+	let half_length = cfg.cart_length * 0.5;
+	let mut hitch = offset(player.position, player.rotation_y, -cfg.truck_hitch_offset);
+	let mut towing_heading = player.rotation_y;
+	for order in 1..target_length {
+		let heading = player.trailer.get(order)
+			.and_then(|old| {
+				let (dx, dz) = (hitch.x - old.x, hitch.z - old.z);
+				(dx * dx + dz * dz > 1e-6).then(|| dx.atan2(dz))
+			})
+			.or_else(|| player.trailer_headings.get(order).copied())
+			.unwrap_or(towing_heading);
+		let heading = towing_heading + clamp_hitch_angle(heading - towing_heading, cfg.max_hitch_angle);
+		let mut cart = offset(hitch, heading, -cfg.hitch_length());
+		cart.y = HOVER_HEIGHT + map.ground_height(cart.x, cart.z);
+		trailer.push_back(cart);
+		headings.push_back(heading);
+		hitch = offset(cart, heading, -half_length);
+		towing_heading = heading;
+	}
+	player.trailer = trailer;
+	player.trailer_headings = headings;
+}
+
+// Replace the trailer with `length` entries lined up straight behind the truck
+pub fn reset_trailer(player: &mut PlayerState, cfg: &GameConfig, map: &MapDef, length: usize) {
+	player.trailer.clear();
+	player.trailer_headings.clear();
+	update_trailer(player, cfg, map, length);
+}
+
+// `point` moved `distance` along yaw `heading`
+fn offset(point: Vec3, heading: f32, distance: f32) -> Vec3 {
+	Vec3 { x: point.x + heading.sin() * distance, y: point.y, z: point.z + heading.cos() * distance }
+}
+
+// Wrap a relative yaw into -PI..PI and limit it to +-max
+fn clamp_hitch_angle(angle: f32, max: f32) -> f32 {
+	let wrapped = (angle + PI).rem_euclid(TAU) - PI;
+	wrapped.clamp(-max, max)
+}